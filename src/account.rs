@@ -0,0 +1,199 @@
+use alloy::primitives::{Address, U256};
+use chrono::{DateTime, Utc};
+use polymarket_client_sdk::types::{
+    AssetType, BalanceAllowanceRequestBuilder, OrdersRequest, Side,
+};
+use polymarket_client_sdk::{contract_config, POLYGON};
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::HashMap;
+use std::str::FromStr as _;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::copier::SharedClient;
+use crate::gates::SkipReason;
+use crate::util::{format_balance, USDC_DECIMALS};
+
+/// Cursor the CLOB returns on the last page of a listing.
+const END_CURSOR: &str = "LTE=";
+
+/// Collateral as the CLOB last reported it, plus what our own copies have committed since.
+#[derive(Debug, Default)]
+struct Snapshot {
+    /// USDC balance, in whole units.
+    balance: Decimal,
+    /// USDC each exchange may spend, in whole units.
+    allowances: HashMap<Address, Decimal>,
+    /// USDC locked by our open BUY orders on the book.
+    open_orders: Decimal,
+    /// USDC held back for copies in flight, by reservation id.
+    reservations: HashMap<u64, Decimal>,
+    next_reservation: u64,
+    refreshed_at: Option<DateTime<Utc>>,
+    /// Why new buys are refused, set by the balance monitor below the collateral floor.
+    buying_paused: Option<String>,
+}
+
+impl Snapshot {
+    fn reserved(&self) -> Decimal {
+        self.reservations.values().copied().sum()
+    }
+
+    /// Collateral the exchange can pull for a new order: the balance, capped by its allowance,
+    /// minus open orders and reservations.
+    fn available(&self, exchange: Address) -> Decimal {
+        let spendable = match self.allowances.get(&exchange) {
+            Some(allowance) => self.balance.min(*allowance),
+            None => self.balance,
+        };
+        (spendable - self.open_orders - self.reserved()).max(Decimal::ZERO)
+    }
+}
+
+/// USDC held back for one copy until [`AccountState::settle`] releases it.
+#[derive(Debug)]
+pub struct Reservation {
+    id: u64,
+    /// What the copy may spend; below the planned amount if it was downsized.
+    pub amount: Decimal,
+}
+
+/// Cached view of our CLOB collateral, from the balance-allowance endpoint and our open orders,
+/// that the copy path consults before building an order. Refreshed on a timer and after fills.
+pub struct AccountState {
+    client: SharedClient,
+    snapshot: Mutex<Snapshot>,
+}
+
+impl AccountState {
+    pub fn new(client: SharedClient) -> Self {
+        Self {
+            client,
+            snapshot: Mutex::new(Snapshot::default()),
+        }
+    }
+
+    /// Reloads the balance and allowances, and the USDC committed to our open BUY orders.
+    pub async fn refresh(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let request = BalanceAllowanceRequestBuilder::default()
+            .asset_type(AssetType::Collateral)
+            .build()?;
+        // Read the client on every refresh, so credentials renewed by the order path are used.
+        let client = self.client.get();
+        let response = client.balance_allowance(&request).await?;
+        let scale = Decimal::from(10u64.pow(USDC_DECIMALS as u32));
+        let allowances = response
+            .allowances
+            .iter()
+            .map(|(exchange, allowance)| {
+                // Unlimited allowances overflow a Decimal; anything above the balance is as good.
+                let allowance = U256::from_str(allowance)
+                    .ok()
+                    .map(|units| format_balance(units, USDC_DECIMALS))
+                    .and_then(|whole| Decimal::from_str(&whole).ok())
+                    .unwrap_or(Decimal::MAX);
+                (*exchange, allowance)
+            })
+            .collect();
+
+        let mut open_orders = Decimal::ZERO;
+        let mut cursor = None;
+        loop {
+            let page = client.orders(&OrdersRequest::default(), cursor).await?;
+            open_orders += page
+                .data
+                .iter()
+                .filter(|order| order.side == Side::Buy)
+                .map(|order| (order.original_size - order.size_matched) * order.price)
+                .sum::<Decimal>();
+            if page.next_cursor.is_empty() || page.next_cursor == END_CURSOR {
+                break;
+            }
+            cursor = Some(page.next_cursor);
+        }
+
+        let mut snapshot = self.snapshot.lock().unwrap();
+        snapshot.balance = response.balance / scale;
+        snapshot.allowances = allowances;
+        snapshot.open_orders = open_orders;
+        snapshot.refreshed_at = Some(Utc::now());
+        Ok(())
+    }
+
+    /// Holds back up to `amount` USDC for a buy on the standard or neg-risk exchange. If less is
+    /// available, the copy is downsized to what is, rounded down to cents; below `min_amount` it
+    /// is skipped. Before the first successful refresh nothing is known, so the full amount is
+    /// reserved and the CLOB has the final say.
+    pub fn reserve_buy(
+        &self,
+        amount: Decimal,
+        min_amount: Decimal,
+        neg_risk: bool,
+    ) -> Result<Reservation, SkipReason> {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        if let Some(reason) = &snapshot.buying_paused {
+            return Err(SkipReason::ExecutionPaused(reason.clone()));
+        }
+        let amount = match (snapshot.refreshed_at, contract_config(POLYGON, neg_risk)) {
+            (Some(_), Some(config)) => {
+                let available = snapshot
+                    .available(config.exchange)
+                    .round_dp_with_strategy(2, RoundingStrategy::ToZero);
+                if available < min_amount {
+                    return Err(SkipReason::InsufficientCollateral { available, amount });
+                }
+                amount.min(available)
+            }
+            _ => amount,
+        };
+        let id = snapshot.next_reservation;
+        snapshot.next_reservation += 1;
+        snapshot.reservations.insert(id, amount);
+        Ok(Reservation { id, amount })
+    }
+
+    /// Releases `reservation` once its copy is done, deducting what it `spent` from the cached
+    /// balance until the next refresh.
+    pub fn settle(&self, reservation: Reservation, spent: Decimal) {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        snapshot.reservations.remove(&reservation.id);
+        snapshot.balance = (snapshot.balance - spent).max(Decimal::ZERO);
+    }
+
+    /// Refuses new buys with `reason` until called again with `None`.
+    pub fn pause_buying(&self, reason: Option<String>) {
+        self.snapshot.lock().unwrap().buying_paused = reason;
+    }
+
+    /// One line for the periodic report.
+    pub fn report(&self) -> String {
+        let snapshot = self.snapshot.lock().unwrap();
+        let report = match snapshot.refreshed_at {
+            Some(at) => format!(
+                "balance {} USDC, open orders {} USDC, reserved {} USDC (as of {})",
+                snapshot.balance.round_dp(2),
+                snapshot.open_orders.round_dp(2),
+                snapshot.reserved().round_dp(2),
+                at.format("%H:%M:%S")
+            ),
+            None => "not loaded yet".to_string(),
+        };
+        match &snapshot.buying_paused {
+            Some(reason) => format!("{}; buying paused: {}", report, reason),
+            None => report,
+        }
+    }
+
+    /// Refreshes every `interval`, after the first, logging failures; the cached state is kept
+    /// until the next successful refresh.
+    pub async fn run_refresh(self: Arc<Self>, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.refresh().await {
+                eprintln!("Error refreshing account state: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use crate::cli::CliArgs;
+use crate::config::env_opt;
+use crate::notifier::Notifier;
+use crate::store::Store;
+
+/// One executed copy, tagged with the target it copied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CopyFill {
+    pub timestamp: DateTime<Utc>,
+    pub target: String,
+    pub token_id: String,
+    pub condition_id: String,
+    /// The target's own fill price.
+    pub target_price: Decimal,
+    pub requested: Decimal,
+    pub spent: Decimal,
+    pub shares: Decimal,
+    pub paper: bool,
+}
+
+/// Current value of a token: resolved with its payout, or open at a mark price.
+#[derive(Debug, Clone, Copy)]
+pub enum Mark {
+    Resolved(Decimal),
+    Open(Decimal),
+}
+
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub token_id: String,
+    pub condition_id: String,
+    pub shares: Decimal,
+    pub cost: Decimal,
+    pub settled: bool,
+    /// PnL at its last mark since startup.
+    pub unrealized: Decimal,
+}
+
+/// Copied PnL and copy fidelity of one target.
+#[derive(Debug, Clone, Default)]
+pub struct TargetPerformance {
+    /// Target buys that reached the copier since startup.
+    pub seen: usize,
+    pub copies: usize,
+    pub requested: Decimal,
+    pub spent: Decimal,
+    /// `Σ (our price - target price) × shares`: what trailing the target cost us.
+    pub slippage_cost: Decimal,
+    pub realized_pnl: Decimal,
+    pub unrealized_pnl: Decimal,
+    pub peak_pnl: Decimal,
+    pub max_drawdown: Decimal,
+    /// Consecutive resolved positions that lost money.
+    pub losing_streak: usize,
+    pub paused: Option<String>,
+    pub positions: Vec<Position>,
+}
+
+impl TargetPerformance {
+    pub fn pnl(&self) -> Decimal {
+        self.realized_pnl + self.unrealized_pnl
+    }
+
+    pub fn drawdown(&self) -> Decimal {
+        self.peak_pnl - self.pnl()
+    }
+
+    /// Share of the requested USDC that was actually filled.
+    pub fn fill_rate(&self) -> Option<Decimal> {
+        (!self.requested.is_zero()).then(|| self.spent / self.requested)
+    }
+
+    /// Average price paid above the target's fill, per share.
+    pub fn avg_slippage(&self) -> Option<Decimal> {
+        let shares = self.positions.iter().map(|p| p.shares).sum::<Decimal>();
+        (!shares.is_zero()).then(|| self.slippage_cost / shares)
+    }
+
+    fn apply(&mut self, fill: &CopyFill) {
+        self.copies += 1;
+        self.requested += fill.requested;
+        self.spent += fill.spent;
+        if !fill.shares.is_zero() {
+            self.slippage_cost += fill.spent - fill.target_price * fill.shares;
+        }
+        match self
+            .positions
+            .iter_mut()
+            .find(|p| p.token_id == fill.token_id && !p.settled)
+        {
+            Some(position) => {
+                position.shares += fill.shares;
+                position.cost += fill.spent;
+            }
+            None => self.positions.push(Position {
+                token_id: fill.token_id.clone(),
+                condition_id: fill.condition_id.clone(),
+                shares: fill.shares,
+                cost: fill.spent,
+                settled: false,
+                unrealized: Decimal::ZERO,
+            }),
+        }
+    }
+}
+
+/// Thresholds on a target's copied PnL that pause copying it.
+#[derive(Debug, Clone, Default)]
+pub struct PauseLimits {
+    pub max_drawdown: Option<Decimal>,
+    pub max_losing_streak: Option<usize>,
+}
+
+impl PauseLimits {
+    /// Reads `PAUSE_MAX_DRAWDOWN_USDC` and `PAUSE_MAX_LOSING_STREAK`; unset disables each.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self {
+            max_drawdown: env_opt("PAUSE_MAX_DRAWDOWN_USDC")?,
+            max_losing_streak: env_opt("PAUSE_MAX_LOSING_STREAK")?,
+        })
+    }
+
+    fn breach(&self, performance: &TargetPerformance) -> Option<String> {
+        if let Some(max) = self.max_drawdown {
+            if performance.drawdown() >= max {
+                return Some(format!(
+                    "drawdown {} USDC reached the {} USDC limit",
+                    performance.drawdown().round_dp(2),
+                    max
+                ));
+            }
+        }
+        if let Some(max) = self.max_losing_streak {
+            if performance.losing_streak >= max {
+                return Some(format!(
+                    "{} losing positions in a row reached the limit of {}",
+                    performance.losing_streak, max
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Per-target attribution of copied orders and positions. Fills, positions and stats are kept in
+/// the [`Store`] and restored on startup, pauses included. Paper and live copies are tracked
+/// apart, so simulated losses never pause live copying; an instance tracks one of the two.
+pub struct Attribution {
+    store: Arc<Store>,
+    limits: PauseLimits,
+    notifier: Notifier,
+    /// Whether this tracks paper fills rather than live ones.
+    paper: bool,
+    targets: Mutex<BTreeMap<String, TargetPerformance>>,
+}
+
+impl Attribution {
+    /// Reads the [`PauseLimits`] and restores the paper or live per-target state from `store`.
+    pub fn from_env(
+        notifier: Notifier,
+        store: Arc<Store>,
+        paper: bool,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Self::open(store, PauseLimits::from_env()?, notifier, paper)
+    }
+
+    pub fn open(
+        store: Arc<Store>,
+        limits: PauseLimits,
+        notifier: Notifier,
+        paper: bool,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let targets = store.targets(paper)?;
+        for (target, performance) in &targets {
+            let open = performance.positions.iter().filter(|p| !p.settled).count();
+            println!(
+                "Restored {}{}: {} copies, {} open positions{}",
+                target,
+                mode_label(paper),
+                performance.copies,
+                open,
+                performance
+                    .paused
+                    .as_ref()
+                    .map_or_else(String::new, |reason| format!(", paused: {}", reason))
+            );
+        }
+
+        Ok(Self {
+            store,
+            limits,
+            notifier,
+            paper,
+            targets: Mutex::new(targets),
+        })
+    }
+
+    /// Why copying `target` is paused, if it is.
+    pub fn paused(&self, target: &str) -> Option<String> {
+        self.targets
+            .lock()
+            .unwrap()
+            .get(target)
+            .and_then(|p| p.paused.clone())
+    }
+
+    pub fn record_seen(&self, target: &str) {
+        self.targets
+            .lock()
+            .unwrap()
+            .entry(target.to_string())
+            .or_default()
+            .seen += 1;
+    }
+
+    /// Adds `fill` to its target's stats; fills of the other mode are ignored.
+    pub fn record_fill(
+        &self,
+        fill: CopyFill,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if fill.paper != self.paper {
+            return Ok(());
+        }
+        let mut targets = self.targets.lock().unwrap();
+        let performance = targets.entry(fill.target.clone()).or_default();
+        performance.apply(&fill);
+        self.store.record_fill(&fill, performance)
+    }
+
+    /// `(token id, condition id)` of every unsettled position.
+    pub fn open_tokens(&self) -> Vec<(String, String)> {
+        let targets = self.targets.lock().unwrap();
+        let mut tokens = targets
+            .values()
+            .flat_map(|p| &p.positions)
+            .filter(|p| !p.settled)
+            .map(|p| (p.token_id.clone(), p.condition_id.clone()))
+            .collect::<Vec<_>>();
+        tokens.sort();
+        tokens.dedup();
+        tokens
+    }
+
+    /// Settles resolved positions and revalues open ones with `marks`, then pauses any target
+    /// that breached a limit. Tokens without a mark keep their previous value.
+    pub fn apply_marks(&self, marks: &HashMap<String, Mark>) {
+        let mut targets = self.targets.lock().unwrap();
+        for (target, performance) in targets.iter_mut() {
+            let mut settled = Vec::new();
+            for position in performance.positions.iter_mut().filter(|p| !p.settled) {
+                match marks.get(&position.token_id) {
+                    Some(Mark::Resolved(payout)) => {
+                        position.settled = true;
+                        settled.push(position.shares * payout - position.cost);
+                    }
+                    Some(Mark::Open(price)) => {
+                        position.unrealized = position.shares * price - position.cost
+                    }
+                    None => {}
+                }
+            }
+            for pnl in settled {
+                performance.realized_pnl += pnl;
+                performance.losing_streak = if pnl < Decimal::ZERO {
+                    performance.losing_streak + 1
+                } else {
+                    0
+                };
+            }
+            performance.unrealized_pnl = performance
+                .positions
+                .iter()
+                .filter(|p| !p.settled)
+                .map(|p| p.unrealized)
+                .sum();
+            performance.peak_pnl = performance.peak_pnl.max(performance.pnl());
+            performance.max_drawdown = performance.max_drawdown.max(performance.drawdown());
+
+            if performance.paused.is_none() {
+                if let Some(reason) = self.limits.breach(performance) {
+                    self.notifier.notify(&format!(
+                        "Paused copying {}{}: {} (pnl {} USDC)",
+                        target,
+                        mode_label(self.paper),
+                        reason,
+                        performance.pnl().round_dp(2)
+                    ));
+                    performance.paused = Some(reason);
+                }
+            }
+        }
+        if let Err(e) = self.store.save_targets(&targets, self.paper) {
+            eprintln!("Error saving target stats: {}", e);
+        }
+    }
+
+    pub fn report(&self) {
+        let targets = self.targets.lock().unwrap();
+        for (target, p) in targets.iter() {
+            println!(
+                "[target {}{}] seen {}, copied {}, fill rate {}, avg slippage {}, spent {} USDC, realized {} USDC, unrealized {} USDC, drawdown {} (max {}) USDC, losing streak {}{}",
+                target,
+                mode_label(self.paper),
+                p.seen,
+                p.copies,
+                p.fill_rate()
+                    .map_or_else(|| "-".to_string(), |r| r.round_dp(3).to_string()),
+                p.avg_slippage()
+                    .map_or_else(|| "-".to_string(), |s| s.round_dp(4).to_string()),
+                p.spent.round_dp(2),
+                p.realized_pnl.round_dp(2),
+                p.unrealized_pnl.round_dp(2),
+                p.drawdown().round_dp(2),
+                p.max_drawdown.round_dp(2),
+                p.losing_streak,
+                p.paused
+                    .as_ref()
+                    .map_or_else(String::new, |reason| format!(", PAUSED: {}", reason))
+            );
+        }
+    }
+}
+
+fn mode_label(paper: bool) -> &'static str {
+    if paper {
+        " (paper)"
+    } else {
+        ""
+    }
+}
+
+/// `resume TARGET... [--paper true]`: clears the live pause of each target in the state store, or
+/// with `--paper` its paper pause, resetting its losing streak and measuring drawdown from its
+/// current PnL.
+pub fn resume(args: &CliArgs) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if args.positional.is_empty() {
+        return Err("Usage: resume TARGET... [--paper true]".into());
+    }
+    let paper = args.parse_or("paper", false)?;
+    let names = args
+        .positional
+        .iter()
+        .map(|target| target.to_lowercase())
+        .collect::<Vec<_>>();
+    let store = Store::from_env()?;
+    let mut targets = store.targets(paper)?;
+    for target in &names {
+        let Some(performance) = targets.get_mut(target) else {
+            return Err(format!(
+                "No {} stats stored for target '{}'",
+                if paper { "paper" } else { "live" },
+                target
+            )
+            .into());
+        };
+        match performance.paused.take() {
+            Some(reason) => println!("Resuming {} (was paused: {})", target, reason),
+            None => println!("{} is not paused", target),
+        }
+        performance.losing_streak = 0;
+        performance.peak_pnl = performance.pnl();
+    }
+    targets.retain(|target, _| names.contains(target));
+    store.save_targets(&targets, paper)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use std::path::Path;
+
+    fn losing_fill(paper: bool) -> CopyFill {
+        CopyFill {
+            timestamp: Utc::now(),
+            target: "0xtarget".to_string(),
+            token_id: "1".to_string(),
+            condition_id: "0xcondition".to_string(),
+            target_price: dec!(0.5),
+            requested: dec!(10),
+            spent: dec!(10),
+            shares: dec!(20),
+            paper,
+        }
+    }
+
+    fn open(store: &Arc<Store>, paper: bool) -> Attribution {
+        let limits = PauseLimits {
+            max_drawdown: None,
+            max_losing_streak: Some(1),
+        };
+        Attribution::open(store.clone(), limits, Notifier::from_env(), paper).unwrap()
+    }
+
+    #[test]
+    fn paper_loss_does_not_pause_live_copying() {
+        let store = Arc::new(Store::open(Path::new(":memory:")).unwrap());
+        let paper = open(&store, true);
+        let live = open(&store, false);
+        paper.record_fill(losing_fill(true)).unwrap();
+        live.record_fill(losing_fill(true)).unwrap();
+
+        let marks = HashMap::from([("1".to_string(), Mark::Resolved(Decimal::ZERO))]);
+        paper.apply_marks(&marks);
+        live.apply_marks(&marks);
+        assert!(paper.paused("0xtarget").is_some());
+        assert_eq!(live.paused("0xtarget"), None);
+        assert!(live.open_tokens().is_empty());
+
+        // Restored from the store, each mode keeps its own state.
+        assert!(open(&store, true).paused("0xtarget").is_some());
+        assert_eq!(open(&store, false).paused("0xtarget"), None);
+    }
+
+    #[test]
+    fn settling_one_token_keeps_the_others_marks() {
+        let store = Arc::new(Store::open(Path::new(":memory:")).unwrap());
+        let live = open(&store, false);
+        live.record_fill(losing_fill(false)).unwrap();
+        live.record_fill(CopyFill {
+            token_id: "2".to_string(),
+            ..losing_fill(false)
+        })
+        .unwrap();
+        live.apply_marks(&HashMap::from([
+            ("1".to_string(), Mark::Open(dec!(0.6))),
+            ("2".to_string(), Mark::Open(dec!(0.6))),
+        ]));
+
+        // A redemption pays 1 USDC per share of token 2.
+        live.apply_marks(&HashMap::from([(
+            "2".to_string(),
+            Mark::Resolved(Decimal::ONE),
+        )]));
+        let targets = live.targets.lock().unwrap();
+        let performance = &targets["0xtarget"];
+        assert_eq!(performance.realized_pnl, dec!(10));
+        assert_eq!(performance.unrealized_pnl, dec!(2));
+        assert_eq!(performance.losing_streak, 0);
+    }
+}
@@ -0,0 +1,530 @@
+use chrono::{DateTime, Duration, Utc};
+use futures_util::stream::{self, StreamExt as _};
+use polymarket_client_sdk::types::Side;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::cli::CliArgs;
+use crate::dedup::TradeKey;
+use crate::filters::MarketFilter;
+use crate::gates::SkipReason;
+use crate::market_catalog::{MarketCatalog, MarketInfo};
+use crate::pipeline::Pipeline;
+use crate::sizing::price_to_tick;
+use crate::trade::{payload_timestamp_ms, TargetTrade};
+
+pub const DEFAULT_LOG_PATH: &str = "log.txt";
+pub const DEFAULT_MARKET_CACHE_PATH: &str = "backtest_markets.json";
+const DEFAULT_LATENCY_SECS: i64 = 2;
+const FETCH_CONCURRENCY: usize = 8;
+
+/// A target BUY read back from the activity log.
+#[derive(Debug, Clone)]
+pub struct RecordedTrade {
+    /// Lowercased proxy wallet of the trader.
+    pub wallet: String,
+    /// Lowercased display name of the trader.
+    pub name: String,
+    pub timestamp: DateTime<Utc>,
+    pub trade: TargetTrade,
+}
+
+impl RecordedTrade {
+    pub fn matches(&self, target: &str) -> bool {
+        self.wallet == target || self.name == target
+    }
+}
+
+/// Reads the BUY trades of `targets` (wallets or names, lowercased) from an activity log written
+/// by the bot, one payload per line. Duplicate deliveries and unreadable lines are dropped.
+pub fn read_log(
+    path: &Path,
+    targets: &[String],
+) -> Result<Vec<RecordedTrade>, Box<dyn std::error::Error + Send + Sync>> {
+    let reader = BufReader::new(fs::File::open(path)?);
+    let mut seen = HashSet::new();
+    let mut trades = Vec::new();
+    let mut malformed = 0usize;
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Ok(payload) = serde_json::from_str::<serde_json::Value>(&line) else {
+            malformed += 1;
+            continue;
+        };
+        let field = |name: &str| {
+            payload
+                .get(name)
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_lowercase()
+        };
+        let (wallet, name) = (field("proxyWallet"), field("name"));
+        if !targets.iter().any(|t| *t == wallet || *t == name) {
+            continue;
+        }
+
+        let trade = TargetTrade::from_payload(&payload).ok();
+        let key = trade
+            .as_ref()
+            .and_then(|trade| TradeKey::from_payload(&payload, trade));
+        let (Some(trade), Some(key), Some(timestamp)) = (
+            trade,
+            key,
+            payload_timestamp_ms(&payload).and_then(DateTime::from_timestamp_millis),
+        ) else {
+            malformed += 1;
+            continue;
+        };
+        if trade.side != Side::Buy || trade.condition_id.is_none() || !seen.insert(key) {
+            continue;
+        }
+        trades.push(RecordedTrade {
+            wallet,
+            name,
+            timestamp,
+            trade,
+        });
+    }
+
+    trades.sort_by_key(|t| t.timestamp);
+    println!(
+        "Read {} target buys from {} ({} unreadable lines)",
+        trades.len(),
+        path.display(),
+        malformed
+    );
+    Ok(trades)
+}
+
+/// Recorded trades and the markets they traded in, loaded once and replayable under any config.
+pub struct BacktestData {
+    pub trades: Vec<RecordedTrade>,
+    pub markets: HashMap<String, MarketInfo>,
+}
+
+/// Log file given as the first positional argument, default `log.txt`.
+pub fn log_path(args: &CliArgs) -> PathBuf {
+    PathBuf::from(
+        args.positional
+            .first()
+            .map_or(DEFAULT_LOG_PATH, String::as_str),
+    )
+}
+
+/// Market cache given by `--cache`, default `backtest_markets.json`.
+pub fn market_cache_path(args: &CliArgs) -> PathBuf {
+    PathBuf::from(args.get("cache").unwrap_or(DEFAULT_MARKET_CACHE_PATH))
+}
+
+/// Returns metadata for `condition_ids`, cached in `cache_path`. Resolved markets are never
+/// fetched again; open ones are refreshed on every call.
+pub async fn load_markets(
+    condition_ids: HashSet<String>,
+    cache_path: &Path,
+) -> Result<HashMap<String, MarketInfo>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut markets: HashMap<String, MarketInfo> = if cache_path.exists() {
+        serde_json::from_str(&fs::read_to_string(cache_path)?)?
+    } else {
+        HashMap::new()
+    };
+
+    let missing = condition_ids
+        .into_iter()
+        .filter(|id| !markets.get(id).is_some_and(MarketInfo::is_resolved))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        println!("Fetching {} markets", missing.len());
+        let catalog = MarketCatalog::from_env()?;
+        let fetched = stream::iter(missing)
+            .map(|condition_id| {
+                let catalog = catalog.clone();
+                async move {
+                    let result = catalog.refresh(&condition_id).await;
+                    (condition_id, result)
+                }
+            })
+            .buffer_unordered(FETCH_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+        for (condition_id, result) in fetched {
+            match result {
+                Ok(info) => {
+                    markets.insert(condition_id, (*info).clone());
+                }
+                Err(e) => eprintln!("Error loading market {}: {}", condition_id, e),
+            }
+        }
+        fs::write(cache_path, serde_json::to_string(&markets)?)?;
+    }
+    Ok(markets)
+}
+
+impl BacktestData {
+    /// Reads the log given on the command line and loads every market its target trades touch.
+    pub async fn from_args(
+        args: &CliArgs,
+        targets: &[String],
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let trades = read_log(&log_path(args), targets)?;
+        let condition_ids = trades
+            .iter()
+            .filter_map(|t| t.trade.condition_id.clone())
+            .collect();
+        let markets = load_markets(condition_ids, &market_cache_path(args)).await?;
+        Ok(Self { trades, markets })
+    }
+}
+
+/// How a copy is assumed to fill: `latency_secs` after the target, at the target's price plus
+/// `slippage`, rounded up to the market's tick.
+#[derive(Debug, Clone, Copy)]
+pub struct FillModel {
+    pub latency_secs: i64,
+    pub slippage: Decimal,
+}
+
+impl FillModel {
+    /// Reads `--latency-secs` (default 2) and `--slippage` (default 0).
+    pub fn from_args(args: &CliArgs) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self {
+            latency_secs: args.parse_or("latency-secs", DEFAULT_LATENCY_SECS)?,
+            slippage: args.parse_or("slippage", Decimal::ZERO)?,
+        })
+    }
+}
+
+/// The pipeline configured from the same environment variables as the live bot, minus the
+/// `MARKET_MIN_VOLUME_24H` and `MARKET_MIN_LIQUIDITY` filters. The market cache only holds the
+/// current volume and liquidity, and filtering past trades on them would let the backtest see the
+/// future.
+pub fn backtest_pipeline() -> Result<Pipeline, Box<dyn std::error::Error + Send + Sync>> {
+    let mut pipeline = Pipeline::from_env()?;
+    ignore_current_metrics(&mut pipeline.filter);
+    Ok(pipeline)
+}
+
+/// Drops the volume and liquidity minimums from `filter`.
+fn ignore_current_metrics(filter: &mut MarketFilter) {
+    let volume = filter.min_volume_24h.take();
+    let liquidity = filter.min_liquidity.take();
+    if volume.is_some() || liquidity.is_some() {
+        println!(
+            "Ignoring MARKET_MIN_VOLUME_24H and MARKET_MIN_LIQUIDITY: only current values are known"
+        );
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BacktestConfig {
+    /// Wallets or names to copy, lowercased.
+    pub targets: Vec<String>,
+    pub pipeline: Pipeline,
+    pub fill: FillModel,
+}
+
+impl BacktestConfig {
+    /// Reads `--targets` and the [`FillModel`] options; the pipeline is the
+    /// [`backtest_pipeline`].
+    pub fn from_args(args: &CliArgs) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let targets = args.list("targets");
+        if targets.is_empty() {
+            return Err("--targets is required (comma-separated wallets or names)".into());
+        }
+        Ok(Self {
+            targets,
+            pipeline: backtest_pipeline()?,
+            fill: FillModel::from_args(args)?,
+        })
+    }
+}
+
+/// A copy the backtest placed, valued at resolution or, while the market is open, at the last
+/// price.
+struct SimulatedCopy {
+    cost: Decimal,
+    value: Decimal,
+    resolved: bool,
+    /// When the PnL is realized: the market's end date, or `None` while it is open.
+    settled_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TargetStats {
+    pub copies: usize,
+    pub skipped: usize,
+    /// Copies left out because their PnL is only realized after the settlement cut-off.
+    pub pending: usize,
+    pub resolved: usize,
+    pub wins: usize,
+    /// USDC spent on copies.
+    pub turnover: Decimal,
+    /// Payout of resolved copies plus open copies marked at their last price.
+    pub value: Decimal,
+    /// Largest peak-to-trough fall of cumulative PnL, ordered by settlement time.
+    pub max_drawdown: Decimal,
+}
+
+impl TargetStats {
+    pub fn pnl(&self) -> Decimal {
+        self.value - self.turnover
+    }
+
+    pub fn hit_rate(&self) -> Option<Decimal> {
+        (self.resolved > 0).then(|| Decimal::from(self.wins) / Decimal::from(self.resolved))
+    }
+
+    pub fn roi(&self) -> Option<Decimal> {
+        (!self.turnover.is_zero()).then(|| self.pnl() / self.turnover)
+    }
+
+    /// Totals of `reports` run one after another, with the drawdown of their PnL series
+    /// concatenated in that order.
+    pub fn concat<'a>(reports: impl IntoIterator<Item = &'a BacktestReport>) -> Self {
+        let mut stats = TargetStats::default();
+        let mut settlements = Vec::new();
+        for report in reports {
+            settlements.extend_from_slice(&report.settlements);
+            let other = &report.total;
+            stats.copies += other.copies;
+            stats.skipped += other.skipped;
+            stats.pending += other.pending;
+            stats.resolved += other.resolved;
+            stats.wins += other.wins;
+            stats.turnover += other.turnover;
+            stats.value += other.value;
+        }
+        stats.max_drawdown = drawdown(settlements);
+        stats
+    }
+
+    fn add(&mut self, copy: &SimulatedCopy) {
+        self.copies += 1;
+        self.turnover += copy.cost;
+        self.value += copy.value;
+        if copy.resolved {
+            self.resolved += 1;
+            if copy.value > copy.cost {
+                self.wins += 1;
+            }
+        }
+    }
+}
+
+/// Settlements ordered by time, open positions last.
+fn max_drawdown(settlements: &mut [(Option<DateTime<Utc>>, Decimal)]) -> Decimal {
+    settlements.sort_by_key(|(at, _)| (at.is_none(), *at));
+    drawdown(settlements.iter().map(|(_, pnl)| *pnl))
+}
+
+/// Largest peak-to-trough fall of the cumulative sum of `pnls`, in the order given.
+fn drawdown(pnls: impl IntoIterator<Item = Decimal>) -> Decimal {
+    let mut equity = Decimal::ZERO;
+    let mut peak = Decimal::ZERO;
+    let mut drawdown = Decimal::ZERO;
+    for pnl in pnls {
+        equity += pnl;
+        peak = peak.max(equity);
+        drawdown = drawdown.max(peak - equity);
+    }
+    drawdown
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BacktestReport {
+    pub per_target: BTreeMap<String, TargetStats>,
+    pub total: TargetStats,
+    pub skip_reasons: BTreeMap<&'static str, usize>,
+    /// PnL of every counted copy in settlement order, open copies last.
+    pub settlements: Vec<Decimal>,
+}
+
+/// Formats a ratio as a percentage with one decimal, `-` when undefined.
+pub fn pct(value: Option<Decimal>) -> String {
+    value.map_or_else(
+        || "-".to_string(),
+        |v| (v * Decimal::ONE_HUNDRED).round_dp(1).to_string(),
+    )
+}
+
+impl BacktestReport {
+    pub fn print(&self) {
+        println!(
+            "{:<44} {:>6} {:>7} {:>8} {:>6} {:>10} {:>10} {:>7} {:>9}",
+            "target", "copies", "skipped", "resolved", "hit%", "turnover", "pnl", "roi%", "max dd"
+        );
+        let rows = self
+            .per_target
+            .iter()
+            .map(|(target, stats)| (target.as_str(), stats))
+            .chain([("total", &self.total)]);
+        for (target, stats) in rows {
+            println!(
+                "{:<44} {:>6} {:>7} {:>8} {:>6} {:>10} {:>10} {:>7} {:>9}",
+                target,
+                stats.copies,
+                stats.skipped,
+                stats.resolved,
+                pct(stats.hit_rate()),
+                stats.turnover.round_dp(2).to_string(),
+                stats.pnl().round_dp(2).to_string(),
+                pct(stats.roi()),
+                stats.max_drawdown.round_dp(2).to_string()
+            );
+        }
+        for (reason, count) in &self.skip_reasons {
+            println!("skipped {}: {}", reason, count);
+        }
+    }
+}
+
+fn copy_trade(
+    recorded: &RecordedTrade,
+    market: Option<&MarketInfo>,
+    config: &BacktestConfig,
+) -> Result<SimulatedCopy, SkipReason> {
+    let trade = &recorded.trade;
+    let Some(market) = market else {
+        return Err(SkipReason::MetadataUnavailable(
+            "market not in backtest cache".to_string(),
+        ));
+    };
+    if let Some(max_age_secs) = config.pipeline.gates.max_trade_age_secs {
+        if config.fill.latency_secs > max_age_secs {
+            return Err(SkipReason::Stale {
+                age_secs: config.fill.latency_secs,
+                max_age_secs,
+            });
+        }
+    }
+
+    let entered_at = recorded.timestamp + Duration::seconds(config.fill.latency_secs);
+    let fill_price = price_to_tick(trade.price + config.fill.slippage, market.tick_size);
+    let plan = config
+        .pipeline
+        .plan(trade, market, Some(fill_price), entered_at)?;
+    let shares = plan.amount / fill_price;
+
+    let copy = match market.resolution_price(&trade.asset) {
+        Some(payout) => SimulatedCopy {
+            cost: plan.amount,
+            value: shares * payout,
+            resolved: true,
+            settled_at: Some(
+                market
+                    .end_date
+                    .map_or(entered_at, |end| end.max(entered_at)),
+            ),
+        },
+        None => SimulatedCopy {
+            cost: plan.amount,
+            value: shares * market.last_price(&trade.asset).unwrap_or_default(),
+            resolved: false,
+            settled_at: None,
+        },
+    };
+    Ok(copy)
+}
+
+/// Replays `data` through the copy pipeline under `config`, restricted to trades inside `window`
+/// when given. Network-free, so many configs can be evaluated against one load.
+///
+/// With `settled_by`, only copies settled before that time are counted; open copies and those
+/// settling later are `pending`, so the report holds nothing that was unknown at `settled_by`.
+pub fn simulate(
+    data: &BacktestData,
+    config: &BacktestConfig,
+    window: Option<&Range<DateTime<Utc>>>,
+    settled_by: Option<DateTime<Utc>>,
+) -> BacktestReport {
+    let mut report = BacktestReport::default();
+    let mut settlements = BTreeMap::<&str, Vec<_>>::new();
+
+    for recorded in &data.trades {
+        if window.is_some_and(|w| !w.contains(&recorded.timestamp)) {
+            continue;
+        }
+        let Some(target) = config.targets.iter().find(|t| recorded.matches(t)) else {
+            continue;
+        };
+        let stats = report.per_target.entry(target.clone()).or_default();
+        let market = recorded
+            .trade
+            .condition_id
+            .as_ref()
+            .and_then(|id| data.markets.get(id));
+        match copy_trade(recorded, market, config) {
+            Ok(copy) if settled_by.is_some_and(|by| copy.settled_at.is_none_or(|at| at >= by)) => {
+                stats.pending += 1;
+                report.total.pending += 1;
+            }
+            Ok(copy) => {
+                stats.add(&copy);
+                report.total.add(&copy);
+                settlements
+                    .entry(target.as_str())
+                    .or_default()
+                    .push((copy.settled_at, copy.value - copy.cost));
+            }
+            Err(reason) => {
+                stats.skipped += 1;
+                report.total.skipped += 1;
+                *report.skip_reasons.entry(reason.kind()).or_default() += 1;
+            }
+        }
+    }
+
+    let mut all = Vec::new();
+    for (target, mut target_settlements) in settlements {
+        if let Some(stats) = report.per_target.get_mut(target) {
+            stats.max_drawdown = max_drawdown(&mut target_settlements);
+        }
+        all.extend(target_settlements);
+    }
+    report.total.max_drawdown = max_drawdown(&mut all);
+    report.settlements = all.into_iter().map(|(_, pnl)| pnl).collect();
+    report
+}
+
+/// `backtest [LOG] --targets a,b [--latency-secs N] [--slippage P] [--cache PATH]`
+pub async fn run(args: &CliArgs) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config = BacktestConfig::from_args(args)?;
+    let data = BacktestData::from_args(args, &config.targets).await?;
+    println!(
+        "Backtest: latency {}s, slippage {}, multiplier {}, order size {}-{} USDC",
+        config.fill.latency_secs,
+        config.fill.slippage,
+        config.pipeline.sizing.multiplier,
+        config.pipeline.sizing.min_amount,
+        config.pipeline.sizing.max_amount
+    );
+    simulate(&data, &config, None, None).print();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn backtest_filter_ignores_current_volume_and_liquidity() {
+        let mut filter = MarketFilter {
+            min_volume_24h: Some(dec!(1000)),
+            min_liquidity: Some(dec!(500)),
+            min_entry_price: Some(dec!(0.1)),
+            ..MarketFilter::default()
+        };
+        ignore_current_metrics(&mut filter);
+        assert_eq!(filter.min_volume_24h, None);
+        assert_eq!(filter.min_liquidity, None);
+        assert_eq!(filter.min_entry_price, Some(dec!(0.1)));
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Arguments of an offline subcommand: `--name value` or `--name=value` options and positional
+/// arguments.
+#[derive(Debug, Clone, Default)]
+pub struct CliArgs {
+    pub positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl CliArgs {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Self {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                parsed.positional.push(arg);
+                continue;
+            };
+            let (name, value) = match name.split_once('=') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None => {
+                    let value = match args.peek() {
+                        Some(next) if !next.starts_with("--") => args.next().unwrap_or_default(),
+                        _ => String::new(),
+                    };
+                    (name.to_string(), value)
+                }
+            };
+            parsed.options.insert(name, value);
+        }
+        parsed
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.options
+            .get(name)
+            .map(String::as_str)
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Comma-separated list option, trimmed and lowercased.
+    pub fn list(&self, name: &str) -> Vec<String> {
+        self.get(name)
+            .map(|v| {
+                v.split(',')
+                    .map(|s| s.trim().to_lowercase())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn parse_or<T>(
+        &self,
+        name: &str,
+        default: T,
+    ) -> Result<T, Box<dyn std::error::Error + Send + Sync>>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        match self.get(name) {
+            Some(v) => v
+                .parse::<T>()
+                .map_err(|e| format!("Invalid --{} '{}': {}", name, v, e).into()),
+            None => Ok(default),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::env;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Value of the environment variable `name` parsed as `T`, or `None` when it is unset or empty.
+/// Surrounding whitespace is ignored; a value that does not parse is an error naming the variable.
+pub fn env_opt<T>(name: &str) -> Result<Option<T>, Box<dyn std::error::Error + Send + Sync>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    parse_opt(name, env::var(name).ok().as_deref())
+}
+
+/// Parses `value`, the value of the variable `name`, as [`env_opt`] does.
+fn parse_opt<T>(
+    name: &str,
+    value: Option<&str>,
+) -> Result<Option<T>, Box<dyn std::error::Error + Send + Sync>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match value {
+        Some(v) if !v.trim().is_empty() => v
+            .trim()
+            .parse::<T>()
+            .map(Some)
+            .map_err(|e| format!("Invalid {} '{}': {}", name, v, e).into()),
+        _ => Ok(None),
+    }
+}
+
+/// Value of the environment variable `name` parsed as `T`, or `default` when it is unset or
+/// empty.
+pub fn env_parse<T>(name: &str, default: T) -> Result<T, Box<dyn std::error::Error + Send + Sync>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    Ok(env_opt(name)?.unwrap_or(default))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn parses_trimmed_values_and_treats_empty_as_unset() {
+        assert_eq!(
+            parse_opt::<Decimal>("DECIMAL", Some(" 1.5 ")).unwrap(),
+            Some(dec!(1.5))
+        );
+        assert_eq!(parse_opt::<u64>("EMPTY", Some(" ")).unwrap(), None);
+        assert_eq!(parse_opt::<u64>("UNSET", None).unwrap(), None);
+        let error = parse_opt::<u64>("INVALID", Some("abc")).unwrap_err();
+        assert!(error.to_string().starts_with("Invalid INVALID 'abc'"));
+    }
+}
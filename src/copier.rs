@@ -0,0 +1,482 @@
+use chrono::Utc;
+use polymarket_client_sdk::auth::Normal;
+use polymarket_client_sdk::clob::state::Authenticated;
+use polymarket_client_sdk::clob::Client;
+use polymarket_client_sdk::types::{OrderBookSummaryRequestBuilder, OrderBookSummaryResponse};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::account::AccountState;
+use crate::attribution::{Attribution, CopyFill, Mark};
+use crate::dedup::TradeKey;
+use crate::execution_error::ExecutionError;
+use crate::funder::Funder;
+use crate::gates::SkipReason;
+use crate::market_catalog::MarketCatalog;
+use crate::notifier::Notifier;
+use crate::orders::{limit_price, OrderConfig, OrderIntent, OrderManager, OrderStage, OrderState};
+use crate::paper::{simulate_buy, PaperFill, PaperLedger};
+use crate::pipeline::{CopyPlan, Pipeline};
+use crate::sizing::min_buy_amount;
+use crate::store::Store;
+use crate::trade::TargetTrade;
+
+pub type ClobClient = Client<Authenticated<Normal>>;
+
+/// The authenticated CLOB client, shared by everything that calls the CLOB, and how many times it
+/// has been re-authenticated. Re-authenticating replaces it for every holder at once.
+#[derive(Clone)]
+pub struct SharedClient(Arc<RwLock<(ClobClient, u64)>>);
+
+impl SharedClient {
+    pub fn new(client: ClobClient) -> Self {
+        Self(Arc::new(RwLock::new((client, 0))))
+    }
+
+    /// The current client.
+    pub fn get(&self) -> ClobClient {
+        self.0.read().unwrap().0.clone()
+    }
+
+    /// The current client and its generation.
+    pub fn current(&self) -> (ClobClient, u64) {
+        self.0.read().unwrap().clone()
+    }
+
+    /// Swaps in `client` as the next generation.
+    pub fn replace(&self, client: ClobClient) {
+        let mut current = self.0.write().unwrap();
+        *current = (client, current.1 + 1);
+    }
+}
+
+const DEFAULT_PAPER_LEDGER_PATH: &str = "paper_ledger.jsonl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradingMode {
+    /// Record activity only.
+    Off,
+    /// Run the full pipeline and simulate fills against the live order book.
+    Paper,
+    /// Place real orders.
+    Live,
+}
+
+impl TradingMode {
+    /// Reads `TRADING_MODE` (`off`, `paper` or `live`, default `off`).
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        match env::var("TRADING_MODE")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "off" => Ok(TradingMode::Off),
+            "paper" => Ok(TradingMode::Paper),
+            "live" => Ok(TradingMode::Live),
+            other => {
+                Err(format!("TRADING_MODE must be off, paper or live, got '{}'", other).into())
+            }
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TradingMode::Off => "off",
+            TradingMode::Paper => "paper",
+            TradingMode::Live => "live",
+        }
+    }
+}
+
+/// Turns target trades that passed the cheap gates into copy orders, live or on paper.
+pub struct Copier {
+    client: SharedClient,
+    orders: OrderManager,
+    catalog: MarketCatalog,
+    pipeline: Pipeline,
+    mode: TradingMode,
+    paper: Option<Mutex<PaperLedger>>,
+    attribution: Arc<Attribution>,
+    account: Arc<AccountState>,
+}
+
+impl Copier {
+    pub fn new(
+        client: ClobClient,
+        funder: Funder,
+        catalog: MarketCatalog,
+        pipeline: Pipeline,
+        mode: TradingMode,
+        notifier: Notifier,
+        store: Arc<Store>,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let paper = match mode {
+            TradingMode::Paper => {
+                let path = env::var("PAPER_LEDGER_PATH")
+                    .unwrap_or_else(|_| DEFAULT_PAPER_LEDGER_PATH.to_string());
+                Some(Mutex::new(PaperLedger::open(PathBuf::from(path))?))
+            }
+            _ => None,
+        };
+
+        let client = SharedClient::new(client);
+        Ok(Self {
+            orders: OrderManager::new(
+                client.clone(),
+                funder,
+                OrderConfig::from_env()?,
+                notifier.clone(),
+                Arc::clone(&store),
+            )?,
+            account: Arc::new(AccountState::new(client.clone())),
+            client,
+            catalog,
+            pipeline,
+            mode,
+            paper,
+            attribution: Arc::new(Attribution::from_env(
+                notifier,
+                store,
+                mode == TradingMode::Paper,
+            )?),
+        })
+    }
+
+    pub fn catalog(&self) -> &MarketCatalog {
+        &self.catalog
+    }
+
+    pub fn pipeline(&self) -> &Pipeline {
+        &self.pipeline
+    }
+
+    pub fn account(&self) -> Arc<AccountState> {
+        Arc::clone(&self.account)
+    }
+
+    pub fn attribution(&self) -> Arc<Attribution> {
+        Arc::clone(&self.attribution)
+    }
+
+    async fn order_book(
+        &self,
+        token_id: &str,
+    ) -> Result<OrderBookSummaryResponse, polymarket_client_sdk::error::Error> {
+        let request = OrderBookSummaryRequestBuilder::default()
+            .token_id(token_id)
+            .build()?;
+        self.client.get().order_book(&request).await
+    }
+
+    /// Runs the copy pipeline for `target`'s `trade` and executes the result according to the
+    /// trading mode.
+    ///
+    /// Returns `false` if the copy stopped on a transient error before any order was posted, in
+    /// which case a redelivery of the trade may be copied again; `true` once it is done with.
+    pub async fn handle(&self, target: String, trade: TargetTrade, trade_key: TradeKey) -> bool {
+        let token_id = trade.asset.as_str();
+        let skip = |reason: SkipReason| {
+            println!(
+                "Skipping trade {} by {} on {}: {}",
+                trade_key.transaction_hash, target, token_id, reason
+            );
+            !matches!(reason, SkipReason::MetadataUnavailable(_))
+        };
+
+        self.attribution.record_seen(&target);
+        if let Some(reason) = self.attribution.paused(&target) {
+            return skip(SkipReason::TargetPaused(reason));
+        }
+        if self.mode == TradingMode::Live {
+            if let Some(reason) = self.orders.paused() {
+                return skip(SkipReason::ExecutionPaused(reason));
+            }
+        }
+
+        let market = match &trade.condition_id {
+            Some(condition_id) => self.catalog.market(condition_id).await,
+            None => self.catalog.market_by_token(token_id).await,
+        };
+        let market = match market {
+            Ok(market) => market,
+            Err(e) => return skip(SkipReason::MetadataUnavailable(e.to_string())),
+        };
+        if !market.is_tradable() {
+            return skip(SkipReason::MarketClosed);
+        }
+
+        let book = if self.pipeline.needs_book() || self.mode == TradingMode::Paper {
+            match self.order_book(token_id).await {
+                Ok(book) => Some(book),
+                Err(e) => {
+                    eprintln!(
+                        "Error fetching order book for token_id {}: {:?}",
+                        token_id, e
+                    );
+                    return false;
+                }
+            }
+        } else {
+            None
+        };
+        let best_ask = book
+            .as_ref()
+            .and_then(|book| book.asks.iter().map(|level| level.price).min());
+
+        let plan = match self.pipeline.plan(&trade, &market, best_ask, Utc::now()) {
+            Ok(plan) => plan,
+            Err(reason) => return skip(reason),
+        };
+
+        match self.mode {
+            TradingMode::Off => true,
+            TradingMode::Paper => {
+                if let Some(book) = book {
+                    self.execute_paper(&target, &trade, &plan, &book).await;
+                }
+                true
+            }
+            TradingMode::Live => {
+                let min_amount = min_buy_amount(plan.reference_price, &market);
+                let reserved = self
+                    .account
+                    .reserve_buy(plan.amount, min_amount, market.neg_risk);
+                let reservation = match reserved {
+                    Ok(reservation) => reservation,
+                    Err(reason) => return skip(reason),
+                };
+                let mut plan = plan;
+                if reservation.amount < plan.amount {
+                    println!(
+                        "Downsizing copy of {} by {} from {} to {} USDC to fit available collateral",
+                        trade_key.transaction_hash, target, plan.amount, reservation.amount
+                    );
+                    plan.amount = reservation.amount;
+                }
+                let intent = self.execute_live(&target, &trade, &plan, min_amount).await;
+                self.account.settle(reservation, intent.spent);
+                if !intent.spent.is_zero() {
+                    if let Err(e) = self.account.refresh().await {
+                        eprintln!("Error refreshing account state: {}", e);
+                    }
+                }
+                !intent.spent.is_zero()
+                    || !intent.failure.is_some_and(|failure| {
+                        failure.stage != OrderStage::Post && failure.error.is_transient()
+                    })
+            }
+        }
+    }
+
+    fn record_fill(
+        &self,
+        target: &str,
+        trade: &TargetTrade,
+        plan: &CopyPlan,
+        spent: Decimal,
+        shares: Decimal,
+    ) {
+        let fill = CopyFill {
+            timestamp: Utc::now(),
+            target: target.to_string(),
+            token_id: plan.token_id.clone(),
+            condition_id: plan.condition_id.clone(),
+            target_price: trade.price,
+            requested: plan.amount,
+            spent,
+            shares,
+            paper: self.mode == TradingMode::Paper,
+        };
+        if let Err(e) = self.attribution.record_fill(fill) {
+            eprintln!("Error recording copy fill for {}: {}", target, e);
+        }
+    }
+
+    async fn execute_live(
+        &self,
+        target: &str,
+        trade: &TargetTrade,
+        plan: &CopyPlan,
+        min_amount: Decimal,
+    ) -> OrderIntent {
+        let intent = self
+            .orders
+            .execute(target, &plan.token_id, plan.amount, min_amount)
+            .await;
+        if intent.state == OrderState::Done && !intent.shares.is_zero() {
+            self.record_fill(target, trade, plan, intent.spent, intent.shares);
+        }
+        // The cached metadata still says tradable; refresh it so later trades in this market are
+        // skipped before an order is built.
+        if let Some(ExecutionError::MarketClosed(_)) = intent.failure.as_ref().map(|f| &f.error) {
+            if let Err(e) = self.catalog.refresh(&plan.condition_id).await {
+                eprintln!("Error refreshing market {}: {}", plan.condition_id, e);
+            }
+        }
+        intent
+    }
+
+    /// Prints execution errors by class since startup and whether live copying is paused.
+    pub fn report_execution(&self) {
+        let counts = self.orders.error_counts();
+        let summary = counts
+            .iter()
+            .map(|(kind, count)| format!("{} {}", kind, count))
+            .collect::<Vec<_>>();
+        println!(
+            "[execution] errors: {}{}",
+            if summary.is_empty() {
+                "none".to_string()
+            } else {
+                summary.join(", ")
+            },
+            self.orders
+                .paused()
+                .map_or_else(String::new, |reason| format!(", PAUSED: {}", reason))
+        );
+        if self.mode == TradingMode::Live {
+            println!("[account] {}", self.account.report());
+        }
+    }
+
+    /// Builds and signs the order exactly as live mode would, then fills it against `book`
+    /// instead of posting it.
+    async fn execute_paper(
+        &self,
+        target: &str,
+        trade: &TargetTrade,
+        plan: &CopyPlan,
+        book: &OrderBookSummaryResponse,
+    ) {
+        let market_order = match self.orders.build(&plan.token_id, plan.amount).await {
+            Ok(order) => order,
+            Err(failure) => {
+                eprintln!("[paper] Order for {} failed: {}", plan.token_id, failure);
+                return;
+            }
+        };
+        let Some(limit) = limit_price(&market_order) else {
+            eprintln!(
+                "Error reading limit price from order for token_id {}",
+                plan.token_id
+            );
+            return;
+        };
+        if let Err(failure) = self.orders.sign(market_order).await {
+            eprintln!("[paper] Order for {} failed: {}", plan.token_id, failure);
+            return;
+        }
+
+        let fill = simulate_buy(&book.asks, plan.amount, limit);
+        println!(
+            "[paper] BUY {} USDC of {} copying {} (sized at {}, limit {}): filled {} shares for {} USDC at avg {}{}",
+            plan.amount,
+            plan.token_id,
+            target,
+            plan.reference_price,
+            limit,
+            fill.shares.round_dp(4),
+            fill.spent.round_dp(4),
+            fill.avg_price().map_or_else(|| "-".to_string(), |p| p.round_dp(4).to_string()),
+            if fill.spent < plan.amount { " (partial)" } else { "" }
+        );
+        if fill.shares.is_zero() {
+            return;
+        }
+        self.record_fill(target, trade, plan, fill.spent, fill.shares);
+
+        let record = PaperFill {
+            timestamp: Utc::now(),
+            target: Some(target.to_string()),
+            token_id: plan.token_id.clone(),
+            condition_id: plan.condition_id.clone(),
+            requested: plan.amount,
+            spent: fill.spent,
+            shares: fill.shares,
+        };
+        if let Some(ledger) = &self.paper {
+            if let Err(e) = ledger.lock().unwrap().record(record) {
+                eprintln!("Error recording paper fill: {}", e);
+            }
+        }
+    }
+
+    /// Revalues every target's copied positions, settling resolved markets and marking open ones
+    /// at the best bid, then prints per-target performance. Targets that breach a pause limit are
+    /// paused here.
+    pub async fn report_targets(&self) {
+        let mut marks = HashMap::new();
+        for (token_id, condition_id) in self.attribution.open_tokens() {
+            let resolution = match self.catalog.market(&condition_id).await {
+                Ok(market) => market.resolution_price(&token_id),
+                Err(e) => {
+                    eprintln!("Error loading market {}: {}", condition_id, e);
+                    None
+                }
+            };
+            if let Some(payout) = resolution {
+                marks.insert(token_id, Mark::Resolved(payout));
+                continue;
+            }
+            match self.order_book(&token_id).await {
+                Ok(book) => {
+                    let best_bid = book.bids.iter().map(|level| level.price).max();
+                    marks.insert(token_id, Mark::Open(best_bid.unwrap_or_default()));
+                }
+                Err(e) => eprintln!(
+                    "Error fetching order book for token_id {}: {:?}",
+                    token_id, e
+                ),
+            }
+        }
+        self.attribution.apply_marks(&marks);
+        self.attribution.report();
+    }
+
+    /// Prints paper positions marked at the current best bid, with unrealized PnL.
+    pub async fn report_paper(&self) {
+        let Some(ledger) = &self.paper else {
+            return;
+        };
+        let positions = ledger.lock().unwrap().positions().clone();
+
+        let mut total_cost = Decimal::ZERO;
+        let mut total_value = Decimal::ZERO;
+        for (token_id, position) in &positions {
+            let best_bid = match self.order_book(token_id).await {
+                Ok(book) => book.bids.iter().map(|level| level.price).max(),
+                Err(e) => {
+                    eprintln!(
+                        "Error fetching order book for token_id {}: {:?}",
+                        token_id, e
+                    );
+                    None
+                }
+            };
+            let mark = best_bid.unwrap_or_default();
+            let value = position.shares * mark;
+            total_cost += position.cost;
+            total_value += value;
+            println!(
+                "[paper] {} ({}): {} shares, cost {} USDC, mark {}, value {} USDC, pnl {} USDC",
+                token_id,
+                position.condition_id,
+                position.shares.round_dp(4),
+                position.cost.round_dp(4),
+                mark,
+                value.round_dp(4),
+                (value - position.cost).round_dp(4)
+            );
+        }
+        println!(
+            "[paper] {} positions, cost {} USDC, value {} USDC, unrealized pnl {} USDC",
+            positions.len(),
+            total_cost.round_dp(4),
+            total_value.round_dp(4),
+            (total_value - total_cost).round_dp(4)
+        );
+    }
+}
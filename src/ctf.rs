@@ -0,0 +1,275 @@
+use alloy::contract::{CallBuilder, CallDecoder};
+use alloy::primitives::{Address, B256, U256};
+use alloy::providers::Provider;
+use rust_decimal::Decimal;
+use std::str::FromStr as _;
+
+use crate::cli::CliArgs;
+use crate::gamma::{GammaClient, GammaMarket};
+use crate::tx::TxSender;
+use crate::util::{
+    approve, usdc_units, wallet_provider, IConditionalTokens, INegRiskAdapter, IERC20,
+    NEG_RISK_ADAPTER_ADDRESS, USDC_ADDRESS,
+};
+
+/// Both outcomes of a binary condition: index sets `0b01` and `0b10`.
+fn binary_partition() -> Vec<U256> {
+    vec![U256::from(1), U256::from(2)]
+}
+
+/// Simulates `call` from `owner` with `eth_call`, then sends it through `sender` unless `simulate`
+/// is set. Returns the hash of the mined transaction, or `None` after a simulation.
+pub async fn simulate_then_send<P: Provider, D: CallDecoder>(
+    sender: &TxSender,
+    call: CallBuilder<P, D>,
+    owner: Address,
+    simulate: bool,
+) -> Result<Option<B256>, Box<dyn std::error::Error + Send + Sync>> {
+    let call = call.from(owner);
+    call.call()
+        .await
+        .map_err(|e| format!("simulation reverted: {}", e))?;
+    if simulate {
+        return Ok(None);
+    }
+    let receipt = sender.send(call, owner).await?;
+    Ok(Some(receipt.transaction_hash))
+}
+
+/// Splits `amount` USDC into `amount` shares of every outcome of `condition`. Neg-risk markets
+/// split through the NegRiskAdapter, which must be approved to spend the USDC; standard markets
+/// through the ConditionalTokens contract, which is approved for exactly `amount` first if needed.
+pub async fn split<P: Provider>(
+    provider: &P,
+    sender: &TxSender,
+    owner: Address,
+    condition: B256,
+    neg_risk: bool,
+    amount: U256,
+    simulate: bool,
+) -> Result<Option<B256>, Box<dyn std::error::Error + Send + Sync>> {
+    if neg_risk {
+        let adapter = INegRiskAdapter::new(NEG_RISK_ADAPTER_ADDRESS, provider);
+        simulate_then_send(
+            sender,
+            adapter.splitPosition(condition, amount),
+            owner,
+            simulate,
+        )
+        .await
+    } else {
+        let ctf = ctf_address()?;
+        let usdc = IERC20::new(USDC_ADDRESS, provider);
+        if usdc.allowance(owner, ctf).call().await? < amount {
+            if simulate {
+                return Err("ConditionalTokens is not approved to spend the USDC; \
+                    run without simulation to approve the amount and split"
+                    .into());
+            }
+            approve(sender, &usdc, owner, ctf, amount).await?;
+        }
+        let ctf = IConditionalTokens::new(ctf, provider);
+        let call = ctf.splitPosition(
+            USDC_ADDRESS,
+            B256::ZERO,
+            condition,
+            binary_partition(),
+            amount,
+        );
+        simulate_then_send(sender, call, owner, simulate).await
+    }
+}
+
+/// Merges `amount` complete sets of `condition` back into `amount` USDC.
+pub async fn merge<P: Provider>(
+    provider: &P,
+    sender: &TxSender,
+    owner: Address,
+    condition: B256,
+    neg_risk: bool,
+    amount: U256,
+    simulate: bool,
+) -> Result<Option<B256>, Box<dyn std::error::Error + Send + Sync>> {
+    if neg_risk {
+        let adapter = INegRiskAdapter::new(NEG_RISK_ADAPTER_ADDRESS, provider);
+        simulate_then_send(
+            sender,
+            adapter.mergePositions(condition, amount),
+            owner,
+            simulate,
+        )
+        .await
+    } else {
+        let ctf = IConditionalTokens::new(ctf_address()?, provider);
+        let call = ctf.mergePositions(
+            USDC_ADDRESS,
+            B256::ZERO,
+            condition,
+            binary_partition(),
+            amount,
+        );
+        simulate_then_send(sender, call, owner, simulate).await
+    }
+}
+
+/// Converts `amount` NO shares of each question in `index_set` of the neg-risk event `market_id`
+/// into `amount` YES shares of every other question, plus USDC for all but one of the converted
+/// questions. The NegRiskAdapter must be approved to move the shares.
+pub async fn convert<P: Provider>(
+    provider: &P,
+    sender: &TxSender,
+    owner: Address,
+    market_id: B256,
+    index_set: U256,
+    amount: U256,
+    simulate: bool,
+) -> Result<Option<B256>, Box<dyn std::error::Error + Send + Sync>> {
+    let adapter = INegRiskAdapter::new(NEG_RISK_ADAPTER_ADDRESS, provider);
+    let call = adapter.convertPositions(market_id, index_set, amount);
+    simulate_then_send(sender, call, owner, simulate).await
+}
+
+/// Position of a neg-risk question within its event, from the last byte of its question id.
+fn question_index(market: &GammaMarket) -> Result<u8, Box<dyn std::error::Error + Send + Sync>> {
+    let question_id = market
+        .question_id
+        .as_deref()
+        .ok_or_else(|| format!("Market {} has no question id", market.condition_id))?;
+    Ok(B256::from_str(question_id)?[31])
+}
+
+fn report(operation: &str, simulate: bool, result: Option<B256>) {
+    match result {
+        Some(tx_hash) => println!("{} mined in tx {}", operation, tx_hash),
+        None if simulate => println!("{} simulated successfully; nothing was sent", operation),
+        None => println!("{} done", operation),
+    }
+}
+
+/// Parses `CONDITION_ID AMOUNT` and looks up whether the market is neg-risk.
+async fn condition_args(
+    args: &CliArgs,
+    usage: &str,
+) -> Result<(B256, bool, Decimal), Box<dyn std::error::Error + Send + Sync>> {
+    let [condition_id, amount] = args.positional.as_slice() else {
+        return Err(format!("Usage: {}", usage).into());
+    };
+    let market = GammaClient::from_env().market(condition_id).await?;
+    Ok((
+        B256::from_str(condition_id)?,
+        market.neg_risk,
+        Decimal::from_str(amount)?,
+    ))
+}
+
+/// `split CONDITION_ID AMOUNT [--simulate true|false]`
+pub async fn run_split(args: &CliArgs) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (condition, neg_risk, amount) =
+        condition_args(args, "split CONDITION_ID AMOUNT [--simulate true|false]").await?;
+    let simulate = args.parse_or("simulate", false)?;
+    let (provider, funder) = wallet_provider().await?;
+    let owner = funder.address();
+    let sender = TxSender::from_env(funder)?;
+    let result = split(
+        &provider,
+        &sender,
+        owner,
+        condition,
+        neg_risk,
+        usdc_units(amount)?,
+        simulate,
+    )
+    .await?;
+    report(
+        &format!("Split of {} USDC into {}", amount, condition),
+        simulate,
+        result,
+    );
+    Ok(())
+}
+
+/// `merge CONDITION_ID AMOUNT [--simulate true|false]`
+pub async fn run_merge(args: &CliArgs) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (condition, neg_risk, amount) =
+        condition_args(args, "merge CONDITION_ID AMOUNT [--simulate true|false]").await?;
+    let simulate = args.parse_or("simulate", false)?;
+    let (provider, funder) = wallet_provider().await?;
+    let owner = funder.address();
+    let sender = TxSender::from_env(funder)?;
+    let result = merge(
+        &provider,
+        &sender,
+        owner,
+        condition,
+        neg_risk,
+        usdc_units(amount)?,
+        simulate,
+    )
+    .await?;
+    report(
+        &format!("Merge of {} sets of {}", amount, condition),
+        simulate,
+        result,
+    );
+    Ok(())
+}
+
+/// `convert CONDITION_ID[,CONDITION_ID...] AMOUNT [--simulate true|false]`
+///
+/// Converts the NO shares of the listed questions, which must belong to one neg-risk event.
+pub async fn run_convert(args: &CliArgs) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let usage = "convert CONDITION_ID[,CONDITION_ID...] AMOUNT [--simulate true|false]";
+    let [condition_ids, amount] = args.positional.as_slice() else {
+        return Err(format!("Usage: {}", usage).into());
+    };
+    let amount = Decimal::from_str(amount)?;
+    let simulate = args.parse_or("simulate", false)?;
+
+    let gamma = GammaClient::from_env();
+    let mut market_id = None;
+    let mut index_set = U256::ZERO;
+    for condition_id in condition_ids.split(',').map(str::trim) {
+        let market = gamma.market(condition_id).await?;
+        let id = market
+            .neg_risk_market_id
+            .as_deref()
+            .filter(|_| market.neg_risk)
+            .ok_or_else(|| format!("Market {} is not neg-risk", condition_id))?;
+        let id = B256::from_str(id)?;
+        if market_id.is_some_and(|market_id| market_id != id) {
+            return Err("All conditions must belong to the same neg-risk event".into());
+        }
+        market_id = Some(id);
+        index_set |= U256::from(1) << question_index(&market)? as usize;
+    }
+    let market_id = market_id.ok_or(usage)?;
+
+    let (provider, funder) = wallet_provider().await?;
+    let owner = funder.address();
+    let sender = TxSender::from_env(funder)?;
+    let result = convert(
+        &provider,
+        &sender,
+        owner,
+        market_id,
+        index_set,
+        usdc_units(amount)?,
+        simulate,
+    )
+    .await?;
+    report(
+        &format!(
+            "Conversion of {} NO shares (index set {:#b}) in {}",
+            amount, index_set, market_id
+        ),
+        simulate,
+        result,
+    );
+    Ok(())
+}
+
+fn ctf_address() -> Result<Address, Box<dyn std::error::Error + Send + Sync>> {
+    polymarket_client_sdk::contract_config(polymarket_client_sdk::POLYGON, false)
+        .map(|config| config.conditional_tokens)
+        .ok_or_else(|| "No contract config for Polygon".into())
+}
@@ -0,0 +1,225 @@
+use chrono::Utc;
+use polymarket_client_sdk::types::Side;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use crate::config::env_parse;
+use crate::copier::TradingMode;
+use crate::store::Store;
+use crate::trade::TargetTrade;
+
+const DEFAULT_TTL_SECS: i64 = 6 * 60 * 60;
+
+/// Identity of a single target fill. Two deliveries with the same key are the same trade.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TradeKey {
+    pub transaction_hash: String,
+    pub asset: String,
+    pub side: String,
+    pub size: String,
+    pub price: String,
+}
+
+impl TradeKey {
+    /// Builds the key from an `activity/trades` payload and the `trade` parsed from it. Returns
+    /// `None` when the payload lacks a transaction hash or the side is unknown, since such trades
+    /// cannot be identified reliably. Size and price are the normalized decimals, so `10` and `"10.0"` are the same.
+    pub fn from_payload(payload: &serde_json::Value, trade: &TargetTrade) -> Option<Self> {
+        let transaction_hash = payload
+            .get("transactionHash")?
+            .as_str()?
+            .trim()
+            .to_lowercase();
+        if transaction_hash.is_empty() {
+            return None;
+        }
+        let side = match trade.side {
+            Side::Buy => "BUY",
+            Side::Sell => "SELL",
+            _ => return None,
+        };
+
+        Some(Self {
+            transaction_hash,
+            asset: trade.asset.clone(),
+            side: side.to_string(),
+            size: trade.size.normalize().to_string(),
+            price: trade.price.normalize().to_string(),
+        })
+    }
+}
+
+/// TTL cache of trades that have already been copied.
+///
+/// A trade is [`claim`](DedupCache::claim)ed when it is handed to the copier and only recorded
+/// as processed by [`complete`](DedupCache::complete) once its copy reached a terminal state. A
+/// copy that stopped on a transient error is [`release`](DedupCache::release)d instead, so a
+/// redelivery of the trade is copied again.
+pub struct DedupCache {
+    ttl_secs: i64,
+    seen: Mutex<HashMap<TradeKey, i64>>,
+    in_flight: Mutex<HashSet<TradeKey>>,
+    /// Where keys are persisted, with the trading mode they are kept under.
+    store: Option<(Arc<Store>, TradingMode)>,
+}
+
+impl DedupCache {
+    pub fn new(ttl_secs: i64) -> Self {
+        Self {
+            ttl_secs,
+            seen: Mutex::new(HashMap::new()),
+            in_flight: Mutex::new(HashSet::new()),
+            store: None,
+        }
+    }
+
+    /// Reads `DEDUP_TTL_SECS` and persists keys to `store`, loading the unexpired ones so a
+    /// restart in the same `mode` does not re-copy recent trades.
+    pub fn from_env(
+        store: Arc<Store>,
+        mode: TradingMode,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Self::new(env_parse("DEDUP_TTL_SECS", DEFAULT_TTL_SECS)?).with_store(store, mode)
+    }
+
+    /// Enables persistence to `store`, loading any unexpired keys processed in `mode` from it.
+    /// Keys are kept per mode, so trades only seen in `off` or `paper` mode are still copied live.
+    pub fn with_store(
+        mut self,
+        store: Arc<Store>,
+        mode: TradingMode,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now().timestamp();
+        let seen = store
+            .processed_trades(mode.as_str(), now - self.ttl_secs + 1)?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        println!(
+            "Loaded {} recent trade keys from the state store",
+            seen.len()
+        );
+        self.seen = Mutex::new(seen);
+        self.store = Some((store, mode));
+        Ok(self)
+    }
+
+    /// Marks `key` as being copied and returns `true` if it has not been processed within the
+    /// TTL and is not being copied already. Returns `false` for duplicates, in which case the
+    /// caller must not place an order. Does not touch the store.
+    pub fn claim(&self, key: &TradeKey) -> bool {
+        let now = Utc::now().timestamp();
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, seen_at| now - *seen_at < self.ttl_secs);
+        !seen.contains_key(key) && self.in_flight.lock().unwrap().insert(key.clone())
+    }
+
+    /// Records a claimed `key` as processed and persists it.
+    pub fn complete(&self, key: TradeKey) {
+        let now = Utc::now().timestamp();
+        self.in_flight.lock().unwrap().remove(&key);
+        if let Some((store, mode)) = &self.store {
+            let expire_before = now - self.ttl_secs;
+            if let Err(e) = store.insert_processed_trade(mode.as_str(), &key, now, expire_before) {
+                eprintln!("Error persisting trade key: {}", e);
+            }
+        }
+        self.seen.lock().unwrap().insert(key, now);
+    }
+
+    /// Gives up a claimed `key` without recording it, so a later delivery is copied again.
+    pub fn release(&self, key: &TradeKey) {
+        self.in_flight.lock().unwrap().remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn key_of(payload: serde_json::Value) -> Option<TradeKey> {
+        TradeKey::from_payload(&payload, &TargetTrade::from_payload(&payload).unwrap())
+    }
+
+    fn key() -> TradeKey {
+        key_of(serde_json::json!({
+            "transactionHash": "0xABC",
+            "asset": "1",
+            "side": "buy",
+            "size": 10,
+            "price": "0.5",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn keys_are_kept_per_trading_mode() {
+        let store = Arc::new(Store::open(Path::new(":memory:")).unwrap());
+        let paper = DedupCache::new(60)
+            .with_store(Arc::clone(&store), TradingMode::Paper)
+            .unwrap();
+        assert!(paper.claim(&key()));
+        paper.complete(key());
+        assert!(!paper.claim(&key()));
+
+        let live = DedupCache::new(60)
+            .with_store(Arc::clone(&store), TradingMode::Live)
+            .unwrap();
+        assert!(live.claim(&key()));
+
+        let restarted = DedupCache::new(60)
+            .with_store(store, TradingMode::Paper)
+            .unwrap();
+        assert!(!restarted.claim(&key()));
+    }
+
+    #[test]
+    fn keys_are_normalized_and_require_a_hash() {
+        let key = key();
+        assert_eq!(key.transaction_hash, "0xabc");
+        assert_eq!(key.side, "BUY");
+        assert_eq!(key.size, "10");
+        let redundant = key_of(serde_json::json!({
+            "transactionHash": "0xabc",
+            "asset": "1",
+            "side": "BUY",
+            "size": "10.0",
+            "price": "0.50",
+        }));
+        assert_eq!(redundant, Some(key));
+        assert!(key_of(serde_json::json!({
+            "asset": "1",
+            "side": "BUY",
+            "size": 10,
+            "price": 0.5,
+        }))
+        .is_none());
+    }
+
+    #[test]
+    fn keys_expire_after_the_ttl() {
+        let cache = DedupCache::new(60);
+        assert!(cache.claim(&key()));
+        cache.complete(key());
+        assert!(!cache.claim(&key()));
+
+        let expired = DedupCache::new(0);
+        assert!(expired.claim(&key()));
+        expired.complete(key());
+        assert!(expired.claim(&key()));
+    }
+
+    #[test]
+    fn released_keys_can_be_claimed_again() {
+        let store = Arc::new(Store::open(Path::new(":memory:")).unwrap());
+        let cache = DedupCache::new(60)
+            .with_store(Arc::clone(&store), TradingMode::Live)
+            .unwrap();
+        assert!(cache.claim(&key()));
+        assert!(!cache.claim(&key()));
+        cache.release(&key());
+        assert!(store.processed_trades("live", 0).unwrap().is_empty());
+        assert!(cache.claim(&key()));
+    }
+}
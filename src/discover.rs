@@ -0,0 +1,448 @@
+use chrono::{DateTime, Utc};
+use polymarket_client_sdk::types::Side;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::backtest::{load_markets, log_path, market_cache_path};
+use crate::cli::CliArgs;
+use crate::dedup::TradeKey;
+use crate::market_catalog::MarketInfo;
+use crate::trade::{payload_timestamp_ms, TargetTrade};
+
+const DEFAULT_OUT_PATH: &str = "wallet_ranking.csv";
+const DEFAULT_MIN_TRADES: usize = 5;
+const DEFAULT_TOP: usize = 20;
+
+/// One wallet's activity in the captured log.
+#[derive(Debug, Clone, Serialize)]
+pub struct WalletStats {
+    pub wallet: String,
+    pub name: String,
+    pub trades: usize,
+    pub buys: usize,
+    pub sells: usize,
+    /// USDC traded, buys and sells.
+    pub volume: Decimal,
+    /// Size-weighted average price of the wallet's buys.
+    pub avg_entry_price: Option<Decimal>,
+    /// Distinct markets traded.
+    pub markets: usize,
+    /// Markets with a position that has resolved.
+    pub resolved_markets: usize,
+    /// Sell proceeds plus resolution payout minus buy cost, over resolved positions.
+    pub realized_pnl: Decimal,
+    pub first_trade: DateTime<Utc>,
+    pub last_trade: DateTime<Utc>,
+}
+
+/// Net position in one token, built from the wallet's trades.
+#[derive(Debug, Clone, Default)]
+struct Position {
+    condition_id: String,
+    shares: Decimal,
+    /// Sell proceeds minus buy cost.
+    cash: Decimal,
+}
+
+struct WalletActivity {
+    stats: WalletStats,
+    buy_cost: Decimal,
+    buy_shares: Decimal,
+    markets: HashSet<String>,
+    positions: HashMap<String, Position>,
+}
+
+impl WalletActivity {
+    fn new(wallet: String, name: String, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            stats: WalletStats {
+                wallet,
+                name,
+                trades: 0,
+                buys: 0,
+                sells: 0,
+                volume: Decimal::ZERO,
+                avg_entry_price: None,
+                markets: 0,
+                resolved_markets: 0,
+                realized_pnl: Decimal::ZERO,
+                first_trade: timestamp,
+                last_trade: timestamp,
+            },
+            buy_cost: Decimal::ZERO,
+            buy_shares: Decimal::ZERO,
+            markets: HashSet::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, trade: &TargetTrade, condition_id: &str, timestamp: DateTime<Utc>) {
+        let notional = trade.price * trade.size;
+        let stats = &mut self.stats;
+        stats.trades += 1;
+        stats.volume += notional;
+        stats.first_trade = stats.first_trade.min(timestamp);
+        stats.last_trade = stats.last_trade.max(timestamp);
+        self.markets.insert(condition_id.to_string());
+
+        let position = self.positions.entry(trade.asset.clone()).or_default();
+        position.condition_id = condition_id.to_string();
+        match trade.side {
+            Side::Buy => {
+                stats.buys += 1;
+                self.buy_cost += notional;
+                self.buy_shares += trade.size;
+                position.shares += trade.size;
+                position.cash -= notional;
+            }
+            _ => {
+                stats.sells += 1;
+                position.shares -= trade.size;
+                position.cash += notional;
+            }
+        }
+    }
+
+    /// Settles resolved positions. Positions that sold more than they bought in the log were
+    /// opened before capture started; their cost is unknown, so they are left out.
+    fn finish(mut self, markets: &HashMap<String, MarketInfo>) -> WalletStats {
+        let mut resolved = HashSet::new();
+        for (token_id, position) in &self.positions {
+            if position.shares < Decimal::ZERO {
+                continue;
+            }
+            let Some(payout) = markets
+                .get(&position.condition_id)
+                .and_then(|market| market.resolution_price(token_id))
+            else {
+                continue;
+            };
+            self.stats.realized_pnl += position.cash + position.shares * payout;
+            resolved.insert(position.condition_id.as_str());
+        }
+        self.stats.resolved_markets = resolved.len();
+        self.stats.markets = self.markets.len();
+        self.stats.avg_entry_price =
+            (!self.buy_shares.is_zero()).then(|| self.buy_cost / self.buy_shares);
+        self.stats
+    }
+}
+
+/// A trade read from the activity log.
+struct LoggedTrade {
+    key: TradeKey,
+    wallet: String,
+    name: String,
+    timestamp: DateTime<Utc>,
+    condition_id: String,
+    trade: TargetTrade,
+}
+
+/// Parses one log line, `None` for lines that are not a trade with a wallet and condition id.
+fn parse_line(line: &str) -> Option<LoggedTrade> {
+    let payload = serde_json::from_str::<serde_json::Value>(line).ok()?;
+    let field = |name: &str| {
+        payload
+            .get(name)
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+    let wallet = field("proxyWallet").to_lowercase();
+    let trade = TargetTrade::from_payload(&payload).ok()?;
+    let key = TradeKey::from_payload(&payload, &trade)?;
+    let timestamp = payload_timestamp_ms(&payload).and_then(DateTime::from_timestamp_millis)?;
+    let condition_id = trade.condition_id.clone()?;
+    (!wallet.is_empty()).then(|| LoggedTrade {
+        key,
+        wallet,
+        name: field("name"),
+        timestamp,
+        condition_id,
+        trade,
+    })
+}
+
+/// The trades of the activity log, read incrementally: each [`ActivityLog::update`] parses only
+/// the lines appended since the previous one.
+pub struct ActivityLog {
+    path: PathBuf,
+    /// Bytes of the file read so far, always at a line boundary.
+    offset: u64,
+    trades: Vec<LoggedTrade>,
+    /// Keys of `trades`, to drop duplicate deliveries.
+    seen: HashSet<TradeKey>,
+}
+
+impl ActivityLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            offset: 0,
+            trades: Vec::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Reads the complete lines appended since the last call and forgets trades before `since`.
+    /// Starts over when the file shrank, as after a truncation or rotation.
+    fn update(
+        &mut self,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut file = fs::File::open(&self.path)?;
+        if file.metadata()?.len() < self.offset {
+            *self = Self::new(self.path.clone());
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut reader = BufReader::new(file);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let read = reader.read_until(b'\n', &mut line)?;
+            // A line still being written is read on the next call.
+            if read == 0 || line.last() != Some(&b'\n') {
+                break;
+            }
+            self.offset += read as u64;
+            let Some(trade) = std::str::from_utf8(&line).ok().and_then(parse_line) else {
+                continue;
+            };
+            if since.is_some_and(|since| trade.timestamp < since)
+                || !self.seen.insert(trade.key.clone())
+            {
+                continue;
+            }
+            self.trades.push(trade);
+        }
+
+        if let Some(since) = since {
+            let before = self.trades.len();
+            self.trades.retain(|t| t.timestamp >= since);
+            if self.trades.len() < before {
+                self.seen.clear();
+                self.seen.extend(self.trades.iter().map(|t| t.key.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Aggregates the trades at or after `since` by proxy wallet.
+    fn activity(&self, since: Option<DateTime<Utc>>) -> HashMap<String, WalletActivity> {
+        let mut wallets = HashMap::<String, WalletActivity>::new();
+        for logged in &self.trades {
+            if since.is_some_and(|since| logged.timestamp < since) {
+                continue;
+            }
+            wallets
+                .entry(logged.wallet.clone())
+                .or_insert_with(|| {
+                    WalletActivity::new(
+                        logged.wallet.clone(),
+                        logged.name.clone(),
+                        logged.timestamp,
+                    )
+                })
+                .add(&logged.trade, &logged.condition_id, logged.timestamp);
+        }
+        wallets
+    }
+}
+
+/// Stats of every wallet with at least `min_trades` trades in the log since `since`, reading only
+/// what was appended to `log` since its last use. Resolved positions are settled with markets
+/// cached in `cache_path`; without one, PnL stays at zero.
+pub async fn wallet_stats(
+    log: &mut ActivityLog,
+    since: Option<DateTime<Utc>>,
+    min_trades: usize,
+    cache_path: Option<&Path>,
+) -> Result<Vec<WalletStats>, Box<dyn std::error::Error + Send + Sync>> {
+    // A failed read leaves `log` empty, so the next call reads the file from the start.
+    let mut taken = std::mem::replace(log, ActivityLog::new(log.path.clone()));
+    *log = tokio::task::spawn_blocking(move || taken.update(since).map(|()| taken)).await??;
+    let mut wallets = log.activity(since);
+    let total_wallets = wallets.len();
+    wallets.retain(|_, activity| activity.stats.trades >= min_trades);
+    println!(
+        "{} wallets in {}, {} with at least {} trades",
+        total_wallets,
+        log.path.display(),
+        wallets.len(),
+        min_trades
+    );
+
+    let markets = match cache_path {
+        Some(cache_path) => {
+            let condition_ids = wallets
+                .values()
+                .flat_map(|activity| activity.markets.iter().cloned())
+                .collect();
+            load_markets(condition_ids, cache_path).await?
+        }
+        None => HashMap::new(),
+    };
+    Ok(wallets
+        .into_values()
+        .map(|activity| activity.finish(&markets))
+        .collect())
+}
+
+fn sort_key(stats: &WalletStats, sort_by: &str) -> Decimal {
+    match sort_by {
+        "trades" => Decimal::from(stats.trades),
+        "volume" => stats.volume,
+        "markets" => Decimal::from(stats.markets),
+        _ => stats.realized_pnl,
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn write_csv(path: &Path, ranking: &[WalletStats]) -> std::io::Result<()> {
+    let mut out = String::from(
+        "rank,wallet,name,trades,buys,sells,volume,avg_entry_price,markets,resolved_markets,realized_pnl,first_trade,last_trade\n",
+    );
+    for (i, stats) in ranking.iter().enumerate() {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            i + 1,
+            stats.wallet,
+            csv_field(&stats.name),
+            stats.trades,
+            stats.buys,
+            stats.sells,
+            stats.volume.round_dp(2),
+            stats
+                .avg_entry_price
+                .map(|p| p.round_dp(4).to_string())
+                .unwrap_or_default(),
+            stats.markets,
+            stats.resolved_markets,
+            stats.realized_pnl.round_dp(2),
+            stats.first_trade.to_rfc3339(),
+            stats.last_trade.to_rfc3339()
+        ));
+    }
+    fs::write(path, out)
+}
+
+/// `discover [LOG] [--min-trades N] [--sort-by pnl|volume|trades|markets] [--out PATH]
+/// [--top N] [--resolve true|false] [--cache PATH]`
+///
+/// The output format follows the extension of `--out`: `.json`, otherwise CSV.
+pub async fn run(args: &CliArgs) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let min_trades = args.parse_or("min-trades", DEFAULT_MIN_TRADES)?;
+    let top = args.parse_or("top", DEFAULT_TOP)?;
+    let sort_by = args.get("sort-by").unwrap_or("pnl");
+    if !["pnl", "volume", "trades", "markets"].contains(&sort_by) {
+        return Err(format!(
+            "--sort-by must be pnl, volume, trades or markets, got '{}'",
+            sort_by
+        )
+        .into());
+    }
+    let out_path = PathBuf::from(args.get("out").unwrap_or(DEFAULT_OUT_PATH));
+
+    let cache_path = args
+        .parse_or("resolve", true)?
+        .then(|| market_cache_path(args));
+    let mut log = ActivityLog::new(log_path(args));
+    let mut ranking = wallet_stats(&mut log, None, min_trades, cache_path.as_deref()).await?;
+    ranking.sort_by(|a, b| {
+        sort_key(b, sort_by)
+            .cmp(&sort_key(a, sort_by))
+            .then(b.trades.cmp(&a.trades))
+            .then(a.wallet.cmp(&b.wallet))
+    });
+
+    if out_path.extension().is_some_and(|ext| ext == "json") {
+        fs::write(&out_path, serde_json::to_string_pretty(&ranking)?)?;
+    } else {
+        write_csv(&out_path, &ranking)?;
+    }
+
+    println!(
+        "{:>4} {:<44} {:<20} {:>6} {:>12} {:>6} {:>7} {:>8} {:>10}",
+        "rank", "wallet", "name", "trades", "volume", "entry", "markets", "resolved", "pnl"
+    );
+    for (i, stats) in ranking.iter().take(top).enumerate() {
+        println!(
+            "{:>4} {:<44} {:<20} {:>6} {:>12} {:>6} {:>7} {:>8} {:>10}",
+            i + 1,
+            stats.wallet,
+            stats.name.chars().take(20).collect::<String>(),
+            stats.trades,
+            stats.volume.round_dp(2).to_string(),
+            stats
+                .avg_entry_price
+                .map_or_else(|| "-".to_string(), |p| p.round_dp(3).to_string()),
+            stats.markets,
+            stats.resolved_markets,
+            stats.realized_pnl.round_dp(2).to_string()
+        );
+    }
+    println!("Wrote {} wallets to {}", ranking.len(), out_path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write as _;
+
+    fn line(hash: &str, timestamp: i64) -> String {
+        serde_json::json!({
+            "proxyWallet": "0xWallet",
+            "name": "trader",
+            "transactionHash": hash,
+            "asset": "1",
+            "conditionId": "0xcondition",
+            "side": "BUY",
+            "size": 10,
+            "price": 0.5,
+            "timestamp": timestamp,
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn activity_log_reads_only_appended_lines() {
+        let path = std::env::temp_dir().join(format!("activity-log-{}.txt", std::process::id()));
+        fs::write(
+            &path,
+            format!("{}\n{}\n", line("0x1", 100), line("0x1", 100)),
+        )
+        .unwrap();
+        let mut log = ActivityLog::new(path.clone());
+        log.update(None).unwrap();
+        assert_eq!(log.trades.len(), 1);
+
+        // The second line is still being written.
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{}\n{}", line("0x2", 200), &line("0x3", 300)[..20]).unwrap();
+        log.update(None).unwrap();
+        assert_eq!(log.trades.len(), 2);
+
+        writeln!(file, "{}", &line("0x3", 300)[20..]).unwrap();
+        log.update(DateTime::from_timestamp(150, 0)).unwrap();
+        let activity = log.activity(None);
+        assert_eq!(activity["0xwallet"].stats.trades, 2);
+        assert_eq!(log.offset, fs::metadata(&path).unwrap().len());
+
+        fs::write(&path, format!("{}\n", line("0x4", 400))).unwrap();
+        log.update(None).unwrap();
+        assert_eq!(log.trades.len(), 1);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,194 @@
+use polymarket_client_sdk::error::{Error as SdkError, Kind, Status, Validation};
+use std::fmt;
+
+/// Why the CLOB or the SDK refused to build, sign or fill a copy order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecutionError {
+    /// Not enough USDC, or the exchange is not approved to spend it.
+    InsufficientBalance(String),
+    BelowMinSize(String),
+    InvalidTick(String),
+    /// The market is closed, resolved or not accepting orders.
+    MarketClosed(String),
+    /// Nothing on the book to match the FAK order against.
+    NoLiquidity(String),
+    RateLimited(String),
+    /// The API credentials were rejected.
+    AuthExpired(String),
+    /// Timeouts, connection errors and 5xx responses.
+    Network(String),
+    Other(String),
+}
+
+impl ExecutionError {
+    /// Classifies an SDK error by kind, HTTP status and message.
+    pub fn from_sdk(error: &SdkError) -> Self {
+        match error.kind() {
+            Kind::Status => {
+                let Some(status) = error.downcast_ref::<Status>() else {
+                    return ExecutionError::Other(error.to_string());
+                };
+                match status.status_code.as_u16() {
+                    429 => ExecutionError::RateLimited(status.message.clone()),
+                    401 | 403 => ExecutionError::AuthExpired(status.message.clone()),
+                    code if code >= 500 => ExecutionError::Network(status.to_string()),
+                    _ => Self::from_message(&status.message),
+                }
+            }
+            Kind::Validation => match error.downcast_ref::<Validation>() {
+                Some(validation) => Self::from_message(&validation.reason),
+                None => ExecutionError::Other(error.to_string()),
+            },
+            // Response decoding and signing errors will fail the same way again.
+            Kind::Internal
+                if error.downcast_ref::<serde_json::Error>().is_some()
+                    || error.downcast_ref::<alloy::signers::Error>().is_some() =>
+            {
+                ExecutionError::Other(error.to_string())
+            }
+            Kind::Internal | Kind::Synchronization => ExecutionError::Network(error.to_string()),
+            _ => ExecutionError::Other(error.to_string()),
+        }
+    }
+
+    /// Classifies a rejection message from the CLOB or the order builder.
+    pub fn from_message(message: &str) -> Self {
+        let lower = message.to_lowercase();
+        let has = |needles: &[&str]| needles.iter().any(|needle| lower.contains(needle));
+        let message = message.to_string();
+        // Credential errors first: "API key does not exist" is not a closed market.
+        if has(&["unauthorized", "api key", "api credentials"]) {
+            ExecutionError::AuthExpired(message)
+        } else if has(&["balance", "allowance"]) {
+            ExecutionError::InsufficientBalance(message)
+        } else if has(&["min size", "minimum size", "lower than the minimum"]) {
+            ExecutionError::BelowMinSize(message)
+        } else if has(&["tick size", "tick_size"]) {
+            ExecutionError::InvalidTick(message)
+        } else if has(&[
+            "closed",
+            "not accepting orders",
+            "not yet ready",
+            "does not exist",
+        ]) {
+            ExecutionError::MarketClosed(message)
+        } else if has(&[
+            "no opposing orders",
+            "no orders found to match",
+            "liquidity",
+        ]) {
+            ExecutionError::NoLiquidity(message)
+        } else if has(&["rate limit", "too many requests"]) {
+            ExecutionError::RateLimited(message)
+        } else {
+            ExecutionError::Other(message)
+        }
+    }
+
+    /// Whether the same request may succeed if retried after a backoff.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            ExecutionError::RateLimited(_) | ExecutionError::Network(_)
+        )
+    }
+
+    /// Short label for the class, without the message, for counting errors.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ExecutionError::InsufficientBalance(_) => "insufficient balance",
+            ExecutionError::BelowMinSize(_) => "below min size",
+            ExecutionError::InvalidTick(_) => "invalid tick",
+            ExecutionError::MarketClosed(_) => "market closed",
+            ExecutionError::NoLiquidity(_) => "no liquidity",
+            ExecutionError::RateLimited(_) => "rate limited",
+            ExecutionError::AuthExpired(_) => "auth expired",
+            ExecutionError::Network(_) => "network",
+            ExecutionError::Other(_) => "other",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ExecutionError::InsufficientBalance(m)
+            | ExecutionError::BelowMinSize(m)
+            | ExecutionError::InvalidTick(m)
+            | ExecutionError::MarketClosed(m)
+            | ExecutionError::NoLiquidity(m)
+            | ExecutionError::RateLimited(m)
+            | ExecutionError::AuthExpired(m)
+            | ExecutionError::Network(m)
+            | ExecutionError::Other(m) => m,
+        }
+    }
+}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind(), self.message())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::transports::http::reqwest::{Method, StatusCode};
+
+    fn status(code: u16, message: &str) -> SdkError {
+        SdkError::status(
+            StatusCode::from_u16(code).unwrap(),
+            Method::POST,
+            "/order".to_string(),
+            message,
+        )
+    }
+
+    #[test]
+    fn classifies_rejection_messages() {
+        let kind = |message: &str| ExecutionError::from_message(message).kind();
+        assert_eq!(
+            kind("not enough balance / allowance"),
+            "insufficient balance"
+        );
+        assert_eq!(kind("Size (1) lower than the minimum: 5"), "below min size");
+        assert_eq!(kind("invalid tick size"), "invalid tick");
+        assert_eq!(
+            kind("the market is not yet ready to process new orders"),
+            "market closed"
+        );
+        assert_eq!(
+            kind("no orders found to match with FAK order"),
+            "no liquidity"
+        );
+        assert_eq!(kind("Unauthorized/Invalid api key"), "auth expired");
+        assert_eq!(kind("API key does not exist"), "auth expired");
+        assert_eq!(kind("something else"), "other");
+    }
+
+    #[test]
+    fn classifies_sdk_errors_by_status() {
+        let classify = |error: SdkError| ExecutionError::from_sdk(&error);
+        assert_eq!(
+            classify(status(429, "slow down")),
+            ExecutionError::RateLimited("slow down".to_string())
+        );
+        assert_eq!(classify(status(401, "nope")).kind(), "auth expired");
+        assert_eq!(classify(status(503, "unavailable")).kind(), "network");
+        assert_eq!(
+            classify(status(400, "not enough balance")).kind(),
+            "insufficient balance"
+        );
+        assert_eq!(
+            classify(SdkError::validation("invalid tick size 0.001")).kind(),
+            "invalid tick"
+        );
+    }
+
+    #[test]
+    fn only_rate_limits_and_network_errors_are_transient() {
+        assert!(ExecutionError::RateLimited(String::new()).is_transient());
+        assert!(ExecutionError::Network(String::new()).is_transient());
+        assert!(!ExecutionError::InsufficientBalance(String::new()).is_transient());
+        assert!(!ExecutionError::Other(String::new()).is_transient());
+    }
+}
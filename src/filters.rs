@@ -0,0 +1,257 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use std::env;
+
+use crate::config::env_opt;
+use crate::gates::SkipReason;
+use crate::market_catalog::MarketInfo;
+
+/// Market-level filters evaluated before a copy order is placed.
+#[derive(Debug, Clone, Default)]
+pub struct MarketFilter {
+    pub allow_slugs: Vec<String>,
+    pub deny_slugs: Vec<String>,
+    pub allow_event_slugs: Vec<String>,
+    pub deny_event_slugs: Vec<String>,
+    pub allow_condition_ids: Vec<String>,
+    pub deny_condition_ids: Vec<String>,
+    pub allow_tags: Vec<String>,
+    pub deny_tags: Vec<String>,
+    pub min_volume_24h: Option<Decimal>,
+    pub min_liquidity: Option<Decimal>,
+    pub min_entry_price: Option<Decimal>,
+    pub max_entry_price: Option<Decimal>,
+    pub min_time_to_end_secs: Option<i64>,
+    pub max_time_to_end_secs: Option<i64>,
+}
+
+fn env_list(name: &str) -> Vec<String> {
+    env::var(name)
+        .map(|v| {
+            v.split(',')
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Checks `value` against an allow list (empty allows everything) and a deny list.
+fn check_list(
+    field: &'static str,
+    value: Option<&str>,
+    allow: &[String],
+    deny: &[String],
+) -> Result<(), SkipReason> {
+    let value = value.map(str::to_lowercase);
+    if let Some(v) = &value {
+        if deny.contains(v) {
+            return Err(SkipReason::Denied {
+                field,
+                value: v.clone(),
+            });
+        }
+    }
+    if !allow.is_empty() && !value.as_ref().is_some_and(|v| allow.contains(v)) {
+        return Err(SkipReason::NotAllowed {
+            field,
+            value: value.unwrap_or_default(),
+        });
+    }
+    Ok(())
+}
+
+impl MarketFilter {
+    /// Reads the `MARKET_*`, `MIN_ENTRY_PRICE`/`MAX_ENTRY_PRICE` and
+    /// `MIN_TIME_TO_END_SECS`/`MAX_TIME_TO_END_SECS` variables. Lists are comma-separated and
+    /// matched case-insensitively.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self {
+            allow_slugs: env_list("MARKET_ALLOW_SLUGS"),
+            deny_slugs: env_list("MARKET_DENY_SLUGS"),
+            allow_event_slugs: env_list("MARKET_ALLOW_EVENT_SLUGS"),
+            deny_event_slugs: env_list("MARKET_DENY_EVENT_SLUGS"),
+            allow_condition_ids: env_list("MARKET_ALLOW_CONDITION_IDS"),
+            deny_condition_ids: env_list("MARKET_DENY_CONDITION_IDS"),
+            allow_tags: env_list("MARKET_ALLOW_TAGS"),
+            deny_tags: env_list("MARKET_DENY_TAGS"),
+            min_volume_24h: env_opt("MARKET_MIN_VOLUME_24H")?,
+            min_liquidity: env_opt("MARKET_MIN_LIQUIDITY")?,
+            min_entry_price: env_opt("MIN_ENTRY_PRICE")?,
+            max_entry_price: env_opt("MAX_ENTRY_PRICE")?,
+            min_time_to_end_secs: env_opt("MIN_TIME_TO_END_SECS")?,
+            max_time_to_end_secs: env_opt("MAX_TIME_TO_END_SECS")?,
+        })
+    }
+
+    /// Filters that only need the trade payload: condition id lists and the entry price band.
+    pub fn check_trade(&self, condition_id: &str, entry_price: Decimal) -> Result<(), SkipReason> {
+        check_list(
+            "condition id",
+            Some(condition_id),
+            &self.allow_condition_ids,
+            &self.deny_condition_ids,
+        )?;
+
+        let below = self.min_entry_price.is_some_and(|min| entry_price < min);
+        let above = self.max_entry_price.is_some_and(|max| entry_price > max);
+        if below || above {
+            return Err(SkipReason::PriceOutOfBand {
+                price: entry_price,
+                min: self.min_entry_price,
+                max: self.max_entry_price,
+            });
+        }
+        Ok(())
+    }
+
+    /// Filters that need market metadata from the [`crate::market_catalog::MarketCatalog`].
+    pub fn check_market(&self, market: &MarketInfo, now: DateTime<Utc>) -> Result<(), SkipReason> {
+        check_list(
+            "slug",
+            market.slug.as_deref(),
+            &self.allow_slugs,
+            &self.deny_slugs,
+        )?;
+        check_list(
+            "event slug",
+            market.event_slug.as_deref(),
+            &self.allow_event_slugs,
+            &self.deny_event_slugs,
+        )?;
+
+        let tags = &market.tags;
+        if let Some(tag) = tags.iter().find(|t| self.deny_tags.contains(t)) {
+            return Err(SkipReason::Denied {
+                field: "tag",
+                value: tag.clone(),
+            });
+        }
+        if !self.allow_tags.is_empty() && !tags.iter().any(|t| self.allow_tags.contains(t)) {
+            return Err(SkipReason::NotAllowed {
+                field: "tag",
+                value: tags.join("|"),
+            });
+        }
+
+        if let Some(min) = self.min_volume_24h {
+            let volume = market.volume_24hr.unwrap_or_default();
+            if volume < min {
+                return Err(SkipReason::LowVolume { volume, min });
+            }
+        }
+        if let Some(min) = self.min_liquidity {
+            let liquidity = market.liquidity.unwrap_or_default();
+            if liquidity < min {
+                return Err(SkipReason::LowLiquidity { liquidity, min });
+            }
+        }
+
+        if self.min_time_to_end_secs.is_some() || self.max_time_to_end_secs.is_some() {
+            let Some(end_date) = market.end_date else {
+                return Err(SkipReason::NoEndDate);
+            };
+            let secs_to_end = (end_date - now).num_seconds();
+            let too_soon = self
+                .min_time_to_end_secs
+                .is_some_and(|min| secs_to_end < min);
+            let too_late = self
+                .max_time_to_end_secs
+                .is_some_and(|max| secs_to_end > max);
+            if too_soon || too_late {
+                return Err(SkipReason::TimeToEnd {
+                    secs_to_end,
+                    min: self.min_time_to_end_secs,
+                    max: self.max_time_to_end_secs,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market_catalog::tests::market;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn lists_are_case_insensitive_and_deny_wins() {
+        let filter = MarketFilter {
+            allow_slugs: vec!["will-it-happen".to_string()],
+            deny_tags: vec!["politics".to_string()],
+            ..Default::default()
+        };
+        let mut market = market();
+        market.slug = Some("Will-It-Happen".to_string());
+        assert_eq!(
+            filter.check_market(&market, Utc::now()),
+            Err(SkipReason::Denied {
+                field: "tag",
+                value: "politics".to_string()
+            })
+        );
+
+        market.tags.clear();
+        assert_eq!(filter.check_market(&market, Utc::now()), Ok(()));
+        market.slug = Some("other".to_string());
+        assert_eq!(
+            filter.check_market(&market, Utc::now()),
+            Err(SkipReason::NotAllowed {
+                field: "slug",
+                value: "other".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn entry_price_band_and_condition_ids() {
+        let filter = MarketFilter {
+            deny_condition_ids: vec!["0xdenied".to_string()],
+            min_entry_price: Some(dec!(0.1)),
+            max_entry_price: Some(dec!(0.9)),
+            ..Default::default()
+        };
+        assert_eq!(filter.check_trade("0xcondition", dec!(0.5)), Ok(()));
+        assert_eq!(filter.check_trade("0xcondition", dec!(0.9)), Ok(()));
+        assert!(filter.check_trade("0xcondition", dec!(0.95)).is_err());
+        assert!(filter.check_trade("0xcondition", dec!(0.05)).is_err());
+        assert!(filter.check_trade("0xDENIED", dec!(0.5)).is_err());
+    }
+
+    #[test]
+    fn volume_liquidity_and_time_to_end() {
+        let market = market();
+        let end = market.end_date.unwrap();
+        let filter = MarketFilter {
+            min_volume_24h: Some(dec!(5000)),
+            min_liquidity: Some(dec!(1000)),
+            min_time_to_end_secs: Some(60),
+            max_time_to_end_secs: Some(3600),
+            ..Default::default()
+        };
+        let now = end - chrono::Duration::seconds(600);
+        assert_eq!(filter.check_market(&market, now), Ok(()));
+        assert!(filter.check_market(&market, end).is_err());
+        assert!(filter
+            .check_market(&market, end - chrono::Duration::hours(2))
+            .is_err());
+
+        let mut quiet = market.clone();
+        quiet.volume_24hr = None;
+        assert_eq!(
+            filter.check_market(&quiet, now),
+            Err(SkipReason::LowVolume {
+                volume: Decimal::ZERO,
+                min: dec!(5000)
+            })
+        );
+        let mut open_ended = market;
+        open_ended.end_date = None;
+        assert_eq!(
+            filter.check_market(&open_ended, now),
+            Err(SkipReason::NoEndDate)
+        );
+    }
+}
@@ -0,0 +1,354 @@
+use alloy::contract::RawCallBuilder;
+use alloy::primitives::{address, b256, keccak256, Address, Bytes, B256, U256};
+use alloy::providers::Provider;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::Signer as _;
+use alloy::sol;
+use alloy::sol_types::SolCall as _;
+use polymarket_client_sdk::clob::{Client, Config};
+use polymarket_client_sdk::error::Error as SdkError;
+use polymarket_client_sdk::types::SignatureType;
+use std::env;
+use std::str::FromStr as _;
+
+use crate::copier::ClobClient;
+
+/// Polymarket's factory for the proxy wallets it creates for email and Magic accounts. It forwards
+/// calls to the proxy wallet of whichever EOA calls it.
+const PROXY_WALLET_FACTORY_ADDRESS: Address =
+    address!("0xaB45c5A4B0c941a2F231C04C3f49182e1A254052");
+
+/// Hash of the init code the factory deploys each proxy wallet with, for CREATE2 derivation.
+const PROXY_INIT_CODE_HASH: B256 =
+    b256!("0xd21df8dc65880a8606f09fe0ce3df9b8869287ab0b058be05aa9e8af6330a00b");
+
+/// `ProxyCall.typeCode` for a plain call.
+const PROXY_CALL: u8 = 1;
+
+/// `Enum.Operation.Call` in Safe transactions.
+const SAFE_CALL: u8 = 0;
+
+sol! {
+    #[sol(rpc)]
+    interface IGnosisSafe {
+        function nonce() external view returns (uint256);
+        function getThreshold() external view returns (uint256);
+        function isOwner(address owner) external view returns (bool);
+    }
+
+    // Without `rpc`: the generated instance methods would take too many arguments.
+    interface IGnosisSafeTransactions {
+        function getTransactionHash(
+            address to,
+            uint256 value,
+            bytes calldata data,
+            uint8 operation,
+            uint256 safeTxGas,
+            uint256 baseGas,
+            uint256 gasPrice,
+            address gasToken,
+            address refundReceiver,
+            uint256 _nonce
+        ) external view returns (bytes32);
+        function execTransaction(
+            address to,
+            uint256 value,
+            bytes calldata data,
+            uint8 operation,
+            uint256 safeTxGas,
+            uint256 baseGas,
+            uint256 gasPrice,
+            address gasToken,
+            address refundReceiver,
+            bytes memory signatures
+        ) external payable returns (bool success);
+    }
+
+    #[sol(rpc)]
+    interface IProxyWalletFactory {
+        struct ProxyCall {
+            uint8 typeCode;
+            address to;
+            uint256 value;
+            bytes data;
+        }
+
+        function proxy(ProxyCall[] memory calls) external payable returns (bytes[] memory);
+    }
+}
+
+/// Parses `SIGNATURE_TYPE`: `eoa`, `proxy` or `gnosis-safe`, or the CLOB's `0`, `1` or `2`.
+fn parse_signature_type(
+    value: &str,
+) -> Result<SignatureType, Box<dyn std::error::Error + Send + Sync>> {
+    match value.trim().to_lowercase().as_str() {
+        "" | "eoa" | "0" => Ok(SignatureType::Eoa),
+        "proxy" | "poly_proxy" | "1" => Ok(SignatureType::Proxy),
+        "gnosis-safe" | "gnosis_safe" | "safe" | "2" => Ok(SignatureType::GnosisSafe),
+        other => Err(format!(
+            "SIGNATURE_TYPE must be eoa, proxy or gnosis-safe, got '{}'",
+            other
+        )
+        .into()),
+    }
+}
+
+/// The proxy wallet the factory deploys for `owner`: CREATE2 from the factory, salted with the
+/// hash of the owner's address.
+pub fn proxy_wallet_address(owner: Address) -> Address {
+    PROXY_WALLET_FACTORY_ADDRESS.create2(keccak256(owner.as_slice()), PROXY_INIT_CODE_HASH)
+}
+
+/// The wallet our funds sit in and how the key in `PRIVATE_KEY` controls it: directly as an EOA,
+/// or as the owner of a Polymarket proxy wallet or Gnosis Safe. Orders are signed by the key with
+/// the funder as maker; balances, approvals and redemptions are those of the funder.
+#[derive(Clone)]
+pub struct Funder {
+    signer: PrivateKeySigner,
+    signature_type: SignatureType,
+    address: Address,
+}
+
+impl Funder {
+    /// Reads `SIGNATURE_TYPE` (`eoa`, `proxy` or `gnosis-safe`, or the CLOB's `0`, `1` or `2`;
+    /// default `eoa`) and `FUNDER_ADDRESS`, which is required for the proxy and Safe types and
+    /// not allowed for `eoa`.
+    pub fn from_env(
+        signer: PrivateKeySigner,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let signature_type = parse_signature_type(&env::var("SIGNATURE_TYPE").unwrap_or_default())?;
+        let funder = match env::var("FUNDER_ADDRESS") {
+            Ok(v) if !v.is_empty() => Some(Address::from_str(v.trim())?),
+            _ => None,
+        };
+        Self::new(signer, signature_type, funder)
+    }
+
+    /// Checks the funder against the signature type. A proxy wallet is only accepted at the
+    /// address the factory deploys for the key, since every call is sent to that one.
+    pub fn new(
+        signer: PrivateKeySigner,
+        signature_type: SignatureType,
+        funder: Option<Address>,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let address = match (signature_type, funder) {
+            (SignatureType::Eoa, None) => signer.address(),
+            (SignatureType::Eoa, Some(_)) => {
+                return Err("FUNDER_ADDRESS is set but SIGNATURE_TYPE is eoa".into())
+            }
+            (_, None) | (_, Some(Address::ZERO)) => {
+                return Err(format!(
+                    "SIGNATURE_TYPE {} needs the wallet's address in FUNDER_ADDRESS",
+                    signature_type
+                )
+                .into())
+            }
+            (SignatureType::Proxy, Some(funder))
+                if funder != proxy_wallet_address(signer.address()) =>
+            {
+                return Err(format!(
+                    "FUNDER_ADDRESS {} is not the proxy wallet of {}, which is {}",
+                    funder,
+                    signer.address(),
+                    proxy_wallet_address(signer.address())
+                )
+                .into())
+            }
+            (_, Some(funder)) => funder,
+        };
+        Ok(Self {
+            signer,
+            signature_type,
+            address,
+        })
+    }
+
+    /// The wallet holding our USDC and shares.
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// The EOA of the private key, which signs orders and pays gas.
+    pub fn signer_address(&self) -> Address {
+        self.signer.address()
+    }
+
+    pub fn signer(&self) -> &PrivateKeySigner {
+        &self.signer
+    }
+
+    pub fn signature_type(&self) -> SignatureType {
+        self.signature_type
+    }
+
+    /// Whether transactions from the funder must be executed through a smart wallet.
+    pub fn is_smart_wallet(&self) -> bool {
+        self.signature_type != SignatureType::Eoa
+    }
+
+    /// A CLOB client authenticated with the private key, that signs orders for the funder.
+    pub async fn authenticate(&self, host: &str) -> Result<ClobClient, SdkError> {
+        let builder = Client::new(host, Config::default())?.authentication_builder(&self.signer);
+        let builder = if self.is_smart_wallet() {
+            builder
+                .funder(self.address)
+                .signature_type(self.signature_type)
+        } else {
+            builder
+        };
+        builder.authenticate().await
+    }
+
+    /// Checks the funder on chain: that the key is an owner of the Safe and its only required
+    /// signer, or that the key's proxy wallet, whose address [`Funder::new`] checked, is deployed.
+    pub async fn verify<P: Provider>(
+        &self,
+        provider: &P,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match self.signature_type {
+            SignatureType::GnosisSafe => {
+                let safe = IGnosisSafe::new(self.address, provider);
+                if !safe.isOwner(self.signer_address()).call().await? {
+                    return Err(format!(
+                        "{} is not an owner of the Safe {}",
+                        self.signer_address(),
+                        self.address
+                    )
+                    .into());
+                }
+                let threshold = safe.getThreshold().call().await?;
+                if threshold != U256::from(1) {
+                    return Err(format!(
+                        "The Safe {} needs {} signatures; only 1-of-n Safes are supported",
+                        self.address, threshold
+                    )
+                    .into());
+                }
+            }
+            SignatureType::Proxy if provider.get_code_at(self.address).await?.is_empty() => {
+                return Err(format!(
+                    "No proxy wallet is deployed at {}; trade once on polymarket.com first",
+                    self.address
+                )
+                .into());
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The transaction, sent from the EOA, that makes the funder call `to` with `data`: a Safe
+    /// `execTransaction` at the Safe's current nonce signed by us, with no gas refund, or a
+    /// one-call batch through the proxy wallet factory. Returns the contract to call and the
+    /// calldata.
+    pub async fn wrap_call<P: Provider>(
+        &self,
+        provider: &P,
+        to: Address,
+        data: Bytes,
+    ) -> Result<(Address, Bytes), Box<dyn std::error::Error + Send + Sync>> {
+        match self.signature_type {
+            SignatureType::GnosisSafe => {
+                let safe = IGnosisSafe::new(self.address, provider);
+                let nonce = safe.nonce().call().await?;
+                let hash_call = IGnosisSafeTransactions::getTransactionHashCall {
+                    to,
+                    value: U256::ZERO,
+                    data: data.clone(),
+                    operation: SAFE_CALL,
+                    safeTxGas: U256::ZERO,
+                    baseGas: U256::ZERO,
+                    gasPrice: U256::ZERO,
+                    gasToken: Address::ZERO,
+                    refundReceiver: Address::ZERO,
+                    _nonce: nonce,
+                };
+                let output = RawCallBuilder::new_raw(provider, hash_call.abi_encode().into())
+                    .to(self.address)
+                    .call()
+                    .await?;
+                let hash: B256 =
+                    IGnosisSafeTransactions::getTransactionHashCall::abi_decode_returns(&output)?;
+                let signature = self.signer.sign_hash(&hash).await?;
+                let exec = IGnosisSafeTransactions::execTransactionCall {
+                    to,
+                    value: U256::ZERO,
+                    data,
+                    operation: SAFE_CALL,
+                    safeTxGas: U256::ZERO,
+                    baseGas: U256::ZERO,
+                    gasPrice: U256::ZERO,
+                    gasToken: Address::ZERO,
+                    refundReceiver: Address::ZERO,
+                    signatures: Bytes::from(signature.as_bytes().to_vec()),
+                };
+                Ok((self.address, exec.abi_encode().into()))
+            }
+            SignatureType::Proxy => {
+                let proxy = IProxyWalletFactory::proxyCall {
+                    calls: vec![IProxyWalletFactory::ProxyCall {
+                        typeCode: PROXY_CALL,
+                        to,
+                        value: U256::ZERO,
+                        data,
+                    }],
+                };
+                Ok((PROXY_WALLET_FACTORY_ADDRESS, proxy.abi_encode().into()))
+            }
+            _ => Ok((to, data)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signer() -> PrivateKeySigner {
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_names_and_clob_numbers() {
+        assert_eq!(parse_signature_type("").unwrap(), SignatureType::Eoa);
+        assert_eq!(parse_signature_type("1").unwrap(), SignatureType::Proxy);
+        assert_eq!(
+            parse_signature_type(" Gnosis-Safe ").unwrap(),
+            SignatureType::GnosisSafe
+        );
+        assert!(parse_signature_type("multisig").is_err());
+    }
+
+    #[test]
+    fn eoa_trades_from_the_key() {
+        let funder = Funder::new(signer(), SignatureType::Eoa, None).unwrap();
+        assert_eq!(funder.address(), signer().address());
+        assert!(!funder.is_smart_wallet());
+        assert!(Funder::new(signer(), SignatureType::Eoa, Some(Address::repeat_byte(1))).is_err());
+    }
+
+    #[test]
+    fn smart_wallets_need_a_funder() {
+        assert!(Funder::new(signer(), SignatureType::GnosisSafe, None).is_err());
+        assert!(Funder::new(signer(), SignatureType::GnosisSafe, Some(Address::ZERO)).is_err());
+        let safe = Address::repeat_byte(7);
+        let funder = Funder::new(signer(), SignatureType::GnosisSafe, Some(safe)).unwrap();
+        assert_eq!(funder.address(), safe);
+    }
+
+    #[test]
+    fn proxy_must_be_the_keys_own() {
+        let own = proxy_wallet_address(signer().address());
+        assert_ne!(own, proxy_wallet_address(Address::repeat_byte(1)));
+        let funder = Funder::new(signer(), SignatureType::Proxy, Some(own)).unwrap();
+        assert_eq!(funder.address(), own);
+        let other = Funder::new(
+            signer(),
+            SignatureType::Proxy,
+            Some(Address::repeat_byte(7)),
+        );
+        assert!(other.is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer};
+use std::env;
+
+use crate::trade::parse_decimal;
+
+const DEFAULT_GAMMA_HOST: &str = "https://gamma-api.polymarket.com";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GammaTag {
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub slug: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GammaEvent {
+    #[serde(default)]
+    pub slug: Option<String>,
+}
+
+/// Subset of the Gamma `/markets` response used for copy decisions.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GammaMarket {
+    pub condition_id: String,
+    #[serde(default)]
+    pub slug: Option<String>,
+    #[serde(default)]
+    pub end_date: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "deserialize_opt_decimal")]
+    pub liquidity: Option<Decimal>,
+    #[serde(
+        default,
+        rename = "volume24hr",
+        deserialize_with = "deserialize_opt_decimal"
+    )]
+    pub volume_24hr: Option<Decimal>,
+    #[serde(default)]
+    pub events: Vec<GammaEvent>,
+    #[serde(default)]
+    pub tags: Vec<GammaTag>,
+    #[serde(default)]
+    pub neg_risk: bool,
+    /// Neg-risk markets only: the question's id, whose last byte is its index in the event.
+    #[serde(default, rename = "questionID")]
+    pub question_id: Option<String>,
+    /// Neg-risk markets only: id of the event's market in the NegRiskAdapter.
+    #[serde(default, rename = "negRiskMarketID")]
+    pub neg_risk_market_id: Option<String>,
+}
+
+impl GammaMarket {
+    pub fn event_slug(&self) -> Option<&str> {
+        self.events.iter().find_map(|e| e.slug.as_deref())
+    }
+
+    /// Tag labels and slugs, lower-cased, for matching against filter lists.
+    pub fn tag_names(&self) -> Vec<String> {
+        self.tags
+            .iter()
+            .flat_map(|t| [t.label.as_deref(), t.slug.as_deref()])
+            .flatten()
+            .map(|s| s.to_lowercase())
+            .collect()
+    }
+}
+
+/// Gamma returns numeric fields either as JSON numbers or as strings.
+fn deserialize_opt_decimal<'de, D>(deserializer: D) -> Result<Option<Decimal>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(value.as_ref().and_then(parse_decimal))
+}
+
+/// Typed client for the Gamma markets API.
+#[derive(Clone)]
+pub struct GammaClient {
+    http: reqwest::Client,
+    host: String,
+}
+
+impl GammaClient {
+    pub fn new(host: String) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            host,
+        }
+    }
+
+    /// Reads `GAMMA_HOST`.
+    pub fn from_env() -> Self {
+        Self::new(env::var("GAMMA_HOST").unwrap_or_else(|_| DEFAULT_GAMMA_HOST.to_string()))
+    }
+
+    /// Fetches the market for `condition_id`, including its tags.
+    pub async fn market(
+        &self,
+        condition_id: &str,
+    ) -> Result<GammaMarket, Box<dyn std::error::Error + Send + Sync>> {
+        let markets = self
+            .http
+            .get(format!("{}/markets", self.host))
+            .query(&[("condition_ids", condition_id), ("include_tag", "true")])
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Vec<GammaMarket>>()
+            .await?;
+        markets
+            .into_iter()
+            .find(|m| m.condition_id.eq_ignore_ascii_case(condition_id))
+            .ok_or_else(|| {
+                format!("Gamma returned no market for condition {}", condition_id).into()
+            })
+    }
+
+    /// Fetches the market that lists `token_id` among its CLOB token ids.
+    pub async fn market_by_token(
+        &self,
+        token_id: &str,
+    ) -> Result<GammaMarket, Box<dyn std::error::Error + Send + Sync>> {
+        let markets = self
+            .http
+            .get(format!("{}/markets", self.host))
+            .query(&[("clob_token_ids", token_id), ("include_tag", "true")])
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Vec<GammaMarket>>()
+            .await?;
+        markets
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("Gamma returned no market for token {}", token_id).into())
+    }
+}
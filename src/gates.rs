@@ -0,0 +1,241 @@
+use chrono::Utc;
+use rust_decimal::Decimal;
+use std::fmt;
+
+use crate::config::{env_opt, env_parse};
+use crate::trade::payload_timestamp_ms;
+
+const DEFAULT_MAX_TRADE_AGE_SECS: i64 = 30;
+
+/// Why a target trade was not copied.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipReason {
+    Stale {
+        age_secs: i64,
+        max_age_secs: i64,
+    },
+    PriceDrift {
+        target_price: Decimal,
+        best_ask: Decimal,
+        max_drift: Decimal,
+    },
+    NoAsks,
+    Denied {
+        field: &'static str,
+        value: String,
+    },
+    NotAllowed {
+        field: &'static str,
+        value: String,
+    },
+    PriceOutOfBand {
+        price: Decimal,
+        min: Option<Decimal>,
+        max: Option<Decimal>,
+    },
+    LowVolume {
+        volume: Decimal,
+        min: Decimal,
+    },
+    LowLiquidity {
+        liquidity: Decimal,
+        min: Decimal,
+    },
+    MarketClosed,
+    NoEndDate,
+    TimeToEnd {
+        secs_to_end: i64,
+        min: Option<i64>,
+        max: Option<i64>,
+    },
+    MetadataUnavailable(String),
+    TargetPaused(String),
+    /// Live copying is paused after an execution error, e.g. insufficient balance.
+    ExecutionPaused(String),
+    BelowMinimumSize {
+        amount: Decimal,
+        min_amount: Decimal,
+    },
+    /// Our available USDC, after open orders and copies in flight, is below the market minimum.
+    InsufficientCollateral {
+        available: Decimal,
+        amount: Decimal,
+    },
+}
+
+impl SkipReason {
+    /// Short label for the reason, without the values, for counting skips.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SkipReason::Stale { .. } => "stale",
+            SkipReason::PriceDrift { .. } => "price drift",
+            SkipReason::NoAsks => "no asks",
+            SkipReason::Denied { .. } => "denied",
+            SkipReason::NotAllowed { .. } => "not allowed",
+            SkipReason::PriceOutOfBand { .. } => "price out of band",
+            SkipReason::LowVolume { .. } => "low volume",
+            SkipReason::LowLiquidity { .. } => "low liquidity",
+            SkipReason::MarketClosed => "market closed",
+            SkipReason::NoEndDate => "no end date",
+            SkipReason::TimeToEnd { .. } => "time to end",
+            SkipReason::MetadataUnavailable(_) => "metadata unavailable",
+            SkipReason::TargetPaused(_) => "target paused",
+            SkipReason::ExecutionPaused(_) => "execution paused",
+            SkipReason::BelowMinimumSize { .. } => "below minimum size",
+            SkipReason::InsufficientCollateral { .. } => "insufficient collateral",
+        }
+    }
+}
+
+fn bound<T: fmt::Display>(value: &Option<T>) -> String {
+    value
+        .as_ref()
+        .map_or_else(|| "-".to_string(), ToString::to_string)
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Stale {
+                age_secs,
+                max_age_secs,
+            } => {
+                write!(
+                    f,
+                    "stale: trade is {}s old (max {}s)",
+                    age_secs, max_age_secs
+                )
+            }
+            SkipReason::PriceDrift {
+                target_price,
+                best_ask,
+                max_drift,
+            } => write!(
+                f,
+                "price drift: best ask {} vs target fill {} (max drift {})",
+                best_ask, target_price, max_drift
+            ),
+            SkipReason::NoAsks => write!(f, "no asks on the order book"),
+            SkipReason::Denied { field, value } => write!(f, "{} {} is denied", field, value),
+            SkipReason::NotAllowed { field, value } => {
+                write!(f, "{} '{}' is not in the allow list", field, value)
+            }
+            SkipReason::PriceOutOfBand { price, min, max } => write!(
+                f,
+                "entry price {} outside band [{}, {}]",
+                price,
+                bound(min),
+                bound(max)
+            ),
+            SkipReason::LowVolume { volume, min } => {
+                write!(f, "24h volume {} below minimum {}", volume, min)
+            }
+            SkipReason::LowLiquidity { liquidity, min } => {
+                write!(f, "liquidity {} below minimum {}", liquidity, min)
+            }
+            SkipReason::MarketClosed => write!(f, "market is closed"),
+            SkipReason::NoEndDate => write!(f, "market has no end date"),
+            SkipReason::TimeToEnd {
+                secs_to_end,
+                min,
+                max,
+            } => write!(
+                f,
+                "{}s to resolution outside [{}, {}]",
+                secs_to_end,
+                bound(min),
+                bound(max)
+            ),
+            SkipReason::BelowMinimumSize { amount, min_amount } => write!(
+                f,
+                "order amount {} USDC below market minimum {} USDC",
+                amount, min_amount
+            ),
+            SkipReason::InsufficientCollateral { available, amount } => write!(
+                f,
+                "only {} USDC available for a {} USDC copy",
+                available, amount
+            ),
+            SkipReason::MetadataUnavailable(e) => write!(f, "market metadata unavailable: {}", e),
+            SkipReason::TargetPaused(reason) => write!(f, "target is paused: {}", reason),
+            SkipReason::ExecutionPaused(reason) => {
+                write!(f, "live copying is paused: {}", reason)
+            }
+        }
+    }
+}
+
+/// Freshness and price-drift limits applied before a trade is copied.
+#[derive(Debug, Clone)]
+pub struct GateConfig {
+    pub max_trade_age_secs: Option<i64>,
+    pub max_price_drift: Option<Decimal>,
+}
+
+impl GateConfig {
+    /// Reads `MAX_TRADE_AGE_SECS` (default 30, `0` disables) and `MAX_PRICE_DRIFT` (absolute
+    /// price units, unset disables).
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let max_trade_age_secs = Some(env_parse("MAX_TRADE_AGE_SECS", DEFAULT_MAX_TRADE_AGE_SECS)?)
+            .filter(|secs| *secs > 0);
+        let max_price_drift = env_opt("MAX_PRICE_DRIFT")?;
+
+        Ok(Self {
+            max_trade_age_secs,
+            max_price_drift,
+        })
+    }
+
+    /// Drops trades older than `max_trade_age_secs`. The age is measured from the earlier of the
+    /// message timestamp (milliseconds) and the payload's own trade timestamp (seconds or
+    /// milliseconds), so a backlog replayed after a reconnect is caught either way.
+    pub fn check_freshness(
+        &self,
+        message_timestamp_ms: u64,
+        payload: &serde_json::Value,
+    ) -> Result<(), SkipReason> {
+        let Some(max_age_secs) = self.max_trade_age_secs else {
+            return Ok(());
+        };
+
+        let now_ms = Utc::now().timestamp_millis();
+        let payload_ms = payload_timestamp_ms(payload);
+        let trade_ms = match payload_ms {
+            Some(ts) => ts.min(message_timestamp_ms as i64),
+            None => message_timestamp_ms as i64,
+        };
+
+        let age_secs = (now_ms - trade_ms) / 1000;
+        if age_secs > max_age_secs {
+            return Err(SkipReason::Stale {
+                age_secs,
+                max_age_secs,
+            });
+        }
+        Ok(())
+    }
+
+    /// Compares the current best ask against the target's fill price and skips when it has moved
+    /// against us by more than `max_price_drift`.
+    pub fn check_drift(
+        &self,
+        target_price: Decimal,
+        best_ask: Option<Decimal>,
+    ) -> Result<(), SkipReason> {
+        let Some(max_drift) = self.max_price_drift else {
+            return Ok(());
+        };
+        let Some(best_ask) = best_ask else {
+            return Err(SkipReason::NoAsks);
+        };
+
+        if best_ask - target_price > max_drift {
+            return Err(SkipReason::PriceDrift {
+                target_price,
+                best_ask,
+                max_drift,
+            });
+        }
+        Ok(())
+    }
+}
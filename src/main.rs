@@ -1,67 +1,125 @@
+mod account;
+mod attribution;
+mod backtest;
+mod cli;
+mod config;
+mod copier;
+mod ctf;
+mod dedup;
+mod execution_error;
+mod discover;
+mod filters;
+mod funder;
+mod gamma;
+mod gates;
+mod market_catalog;
+mod monitor;
+mod notifier;
+mod orders;
+mod paper;
+mod pipeline;
+mod sizing;
+mod store;
+mod sweep;
+mod trade;
+mod tx;
 mod real_time_data_client;
+mod rotation;
+mod rpc;
+mod targets;
 mod util;
+mod wallet;
 
 
-use rust_decimal::Decimal;
-use rust_decimal::prelude::FromPrimitive;
 use std::str::FromStr as _;
 use std::env;
-use alloy::primitives::U256;
-use alloy::providers::ProviderBuilder;
+use std::time::Duration;
 use alloy::signers::Signer as _;
 use alloy::signers::local::LocalSigner;
-use polymarket_client_sdk::clob::{Client, Config};
-use polymarket_client_sdk::types::{Amount, AssetType, BalanceAllowanceRequestBuilder, OrderType, Side};
+use polymarket_client_sdk::types::Side;
 use polymarket_client_sdk::{POLYGON, PRIVATE_KEY_VAR, contract_config};
 
 
-use crate::util::set_all_approvals;
-use serde::{Deserialize, Serialize};
-use uuid::Uuid;
-use chrono::Utc;
+use crate::cli::CliArgs;
+use crate::config::env_parse;
+use crate::copier::{Copier, TradingMode};
+use crate::dedup::{DedupCache, TradeKey};
+use crate::funder::Funder;
+use crate::market_catalog::MarketCatalog;
+use crate::monitor::{run_balance_monitor, MonitorConfig};
+use crate::notifier::Notifier;
+use crate::pipeline::Pipeline;
+use crate::rotation::{AutoFollow, AutoFollowConfig};
+use crate::store::Store;
+use crate::targets::Targets;
+use crate::trade::TargetTrade;
+use crate::tx::TxSender;
+use crate::util::{
+    ensure_approvals, run_allowance_top_ups, run_redemptions, ApprovalConfig, ApprovalMode,
+};
 use std::fs::OpenOptions;
 use std::io::Write;
+use std::sync::Arc;
 use crate::real_time_data_client::{
     ConnectionStatus, Message, RealTimeDataClient, RealTimeDataClientArgs, Subscription,
     SubscriptionMessage,
 };
 
-#[derive(Serialize, Deserialize)]
-struct CredentialsJson {
-    #[serde(alias = "apiKey")]
-    key: Uuid,
-    secret: String,
-    passphrase: String,
-}
-
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     dotenv::dotenv().ok();
-    let enable_trading = false;
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if let Some((command, rest)) = args.split_first() {
+        let cli = CliArgs::parse(rest.iter().cloned());
+        return match command.as_str() {
+            "backtest" => backtest::run(&cli).await,
+            "sweep" => sweep::run(&cli).await,
+            "discover" => discover::run(&cli).await,
+            "resume" => attribution::resume(&cli),
+            "redeem" => util::run_redeem(&cli).await,
+            "split" => ctf::run_split(&cli).await,
+            "merge" => ctf::run_merge(&cli).await,
+            "convert" => ctf::run_convert(&cli).await,
+            "revoke" => wallet::run_revoke(&cli).await,
+            "sweep-wallet" => wallet::run_sweep(&cli).await,
+            other => Err(format!("Unknown command '{}'", other).into()),
+        };
+    }
+    let trading_mode = TradingMode::from_env()?;
     let private_key = std::env::var(PRIVATE_KEY_VAR).expect("Need a private key");
     let signer = LocalSigner::from_str(&private_key)?.with_chain_id(Some(POLYGON));
-    let client = Client::new("https://clob.polymarket.com", Config::default())?
-        .authentication_builder(&signer)
-        .authenticate()
-        .await?;
-
-    let multiplier = env::var("MULTIPLIER").unwrap_or("1".to_string()).parse::<f64>().unwrap();
-    let min_value = Decimal::from_f64(1.0).unwrap();
-    let max_value = Decimal::from_f64(4.0).unwrap();
-    const RPC_URL: &str = "https://polygon-rpc.com";
-    
-    let provider = ProviderBuilder::new()
-        .wallet(signer.clone())
-        .connect(RPC_URL)
-        .await?;
+    let funder = Funder::from_env(signer.clone())?;
+    let client = funder.authenticate("https://clob.polymarket.com").await?;
+
+    let provider = rpc::connect(signer.clone()).await?;
+    funder.verify(&provider).await?;
+    if funder.is_smart_wallet() {
+        println!("Trading for {} wallet {} owned by {}", funder.signature_type(), funder.address(), signer.address());
+    }
 
     let config = contract_config(POLYGON, false).unwrap();
     let neg_risk_config = contract_config(POLYGON, true).unwrap();
-    
-    let allowance_amount = U256::MAX;
-    
-    println!("Setting all approvals for Polymarket trading...");
-    set_all_approvals(&provider, signer.address(), &config, &neg_risk_config, allowance_amount).await?;
+
+    println!("Checking approvals for Polymarket trading...");
+    let tx_sender = Arc::new(TxSender::from_env(funder.clone())?);
+    let mut approvals = ApprovalConfig::from_env()?;
+    if trading_mode != TradingMode::Live {
+        // Only live orders need approvals: report what is missing without sending anything.
+        approvals.mode = ApprovalMode::DryRun;
+    }
+    ensure_approvals(&provider, &tx_sender, funder.address(), config, neg_risk_config, &approvals).await?;
+    let approval_check_secs = env_parse("APPROVAL_CHECK_SECS", 600u64)?;
+    if trading_mode == TradingMode::Live && approvals.mode == ApprovalMode::Apply && approval_check_secs > 0 {
+        tokio::spawn(run_allowance_top_ups(
+            provider.clone(),
+            Arc::clone(&tx_sender),
+            funder.address(),
+            config,
+            neg_risk_config,
+            approvals,
+            Duration::from_secs(approval_check_secs),
+        ));
+    }
 
     let on_connect = Box::new(|| {
         println!("Connected to WebSocket server");
@@ -70,13 +128,85 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let on_status_change = Box::new(|status: ConnectionStatus| {
         println!("Connection status changed: {}", status);
     });
-    const TARGET_WALLET: &str = "afewfdzgre";
+    let targets = Targets::from_env();
+    let notifier = Notifier::from_env();
+    let store = Arc::new(Store::from_env()?);
+    if let Some(config) = AutoFollowConfig::from_env()? {
+        let auto_follow = AutoFollow::new(config, targets.clone(), Arc::clone(&store))?;
+        tokio::spawn(auto_follow.run());
+    }
+    let dedup = Arc::new(DedupCache::from_env(Arc::clone(&store), trading_mode)?);
+    let copier = Arc::new(Copier::new(
+        client.clone(),
+        funder.clone(),
+        MarketCatalog::from_env()?,
+        Pipeline::from_env()?,
+        trading_mode,
+        notifier.clone(),
+        Arc::clone(&store),
+    )?);
+    let redeem_secs = env_parse("REDEEM_INTERVAL_SECS", 3600u64)?;
+    if trading_mode == TradingMode::Live && redeem_secs > 0 {
+        tokio::spawn(run_redemptions(
+            provider.clone(),
+            Arc::clone(&tx_sender),
+            config,
+            store,
+            copier.attribution(),
+            notifier.clone(),
+            Duration::from_secs(redeem_secs),
+        ));
+    }
+
+    if trading_mode == TradingMode::Live {
+        let account = copier.account();
+        if let Err(e) = account.refresh().await {
+            eprintln!("Error loading account state: {}", e);
+        }
+        let refresh_secs = env_parse("ACCOUNT_REFRESH_SECS", 60u64)?;
+        tokio::spawn(Arc::clone(&account).run_refresh(Duration::from_secs(refresh_secs)));
+        let monitor = MonitorConfig::from_env()?;
+        if !monitor.interval.is_zero() {
+            tokio::spawn(run_balance_monitor(
+                provider.clone(),
+                funder.clone(),
+                config,
+                neg_risk_config,
+                monitor,
+                notifier.clone(),
+                account,
+            ));
+        }
+    }
+    if trading_mode == TradingMode::Paper {
+        let copier = Arc::clone(&copier);
+        let report_secs = env_parse("PAPER_REPORT_SECS", 300u64)?;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(report_secs));
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                copier.report_paper().await;
+            }
+        });
+    }
+    if trading_mode != TradingMode::Off {
+        let copier = Arc::clone(&copier);
+        let report_secs = env_parse("ATTRIBUTION_REPORT_SECS", 300u64)?;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(report_secs));
+            loop {
+                interval.tick().await;
+                copier.report_targets().await;
+                copier.report_execution();
+            }
+        });
+    }
 
-    let client_clone = client.clone();
-    let signer_clone = signer.clone();
-    
+    let copier_clone = Arc::clone(&copier);
     let on_message = Box::new(move |message: Message| {
-        if message.payload.get("name").and_then(|v| v.as_str()) == Some(TARGET_WALLET) {
+        let target = targets.matches(&message.payload);
+        if target.is_some() {
             println!("message: {:?}", message.payload.get("proxyWallet"));
         }
         let mut log_file = OpenOptions::new()
@@ -89,86 +219,55 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         .write_all(
             message
                 .payload
-                .unwrap()
                 .to_string()
                 .as_bytes(),
         )
         .unwrap();
 
     log_file.write_all(b"\n").unwrap();
-        if !enable_trading {
+        if trading_mode == TradingMode::Off {
+            return;
+        }
+        let Some(target) = target else {
+            return;
+        };
+        let copier = Arc::clone(&copier_clone);
+        if let Some(condition_id) = message.payload.get("conditionId").and_then(|v| v.as_str()) {
+            copier.catalog().warm(condition_id);
+        }
+        let trade = match TargetTrade::from_payload(&message.payload) {
+            Ok(trade) => trade,
+            Err(e) => {
+                eprintln!("Skipping malformed trade ({}): {}", e, message.payload);
+                return;
+            }
+        };
+        if trade.side != Side::Buy {
             return;
         }
-        let Some(proxy_wallet) = message.payload.get("name")
-            .and_then(|v| v.as_str()) else { return; };
 
-        if proxy_wallet != TARGET_WALLET {
+        let Some(trade_key) = TradeKey::from_payload(&message.payload, &trade) else {
+            eprintln!("Skipping trade without transaction hash: {}", message.payload);
+            return;
+        };
+        if let Err(reason) = copier.pipeline().gates.check_freshness(message.timestamp, &message.payload) {
+            println!("Skipping trade {} on {}: {}", trade_key.transaction_hash, trade_key.asset, reason);
             return;
         }
-        let Some(side) = message.payload.get("side").and_then(|v| v.as_str()) else { return; };
-        if side != "BUY" {
+        if !dedup.claim(&trade_key) {
+            println!("Skipping duplicate trade {} on {}", trade_key.transaction_hash, trade_key.asset);
             return;
         }
-        let Some(size) = message.payload.get("size").and_then(|v| v.as_f64()) else { return; };
-
-        let client = client_clone.clone();
-        let signer = signer_clone.clone();
-        
-        
-            tokio::spawn(async move {
-                let message_payload = message.payload.clone();
-                let token_id = match message_payload.get("asset").and_then(|v| v.as_str()) {
-                    Some(id) => id,
-                    None => return,
-                };
-                let Some(price) = message_payload.get("price").and_then(|v| v.as_f64()) else { return; };
-                let price_decimal = Decimal::from_f64(price).unwrap();
-                let size_decimal = Decimal::from_f64(size).unwrap();
-                let multiplier_decimal = Decimal::from_f64(multiplier).unwrap();
-                let mut buy_amount_value = price_decimal * size_decimal * multiplier_decimal;
-                
-                if buy_amount_value < min_value {
-                    buy_amount_value = min_value;
-                }
-                if buy_amount_value > max_value {
-                    buy_amount_value = max_value;
-                }           
-                
-                let buy_amount = Amount::usdc(buy_amount_value).unwrap();
-                let market_order = match client
-                    .market_order()
-                    .token_id(token_id)
-                    .order_type(OrderType::FAK)
-                    .amount(buy_amount)
-                    .side(Side::Buy)
-                    .build()
-                    .await
-                {
-                    Ok(order) => order,
-                    Err(e) => {
-                        eprintln!("Error building market order: {:?}", e);
-                        return;
-                    }
-                };
-
-                let signed_order = match client.sign(&signer, market_order).await {
-                    Ok(order) => order,
-                    Err(e) => {
-                        eprintln!("Error signing order: {:?}", e);
-                        return;
-                    }
-                };
-
-                match client.post_order(signed_order).await {
-                    Ok(response) => {
-                        println!("Order submitted successfully for token_id {}: {:?}", token_id, response);
-                    }
-                    Err(e) => {
-                        eprintln!("Error submitting order for token_id {}: {:?}", token_id, e);
-                    }
-                }
-            });
-        
+
+        // The key is only persisted once the copy is done with, off the websocket callback.
+        let dedup = Arc::clone(&dedup);
+        tokio::spawn(async move {
+            if copier.handle(target, trade, trade_key.clone()).await {
+                dedup.complete(trade_key);
+            } else {
+                dedup.release(&trade_key);
+            }
+        });
     });
     let mut client = RealTimeDataClient::new(RealTimeDataClientArgs {
         on_connect: Some(on_connect),
@@ -179,7 +278,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         auto_reconnect: Some(true),
     });
 
-    client.connect().await.map_err(|e| Box::new(std::io::Error::new(std::io::ErrorKind::Other, e)) as Box<dyn std::error::Error + Send + Sync>)?;
+    client.connect().await.map_err(|e| Box::new(std::io::Error::other(e)) as Box<dyn std::error::Error + Send + Sync>)?;
 
     client.subscribe(SubscriptionMessage {
         subscriptions: vec![
@@ -196,6 +295,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     tokio::signal::ctrl_c().await?;
     println!("\nShutting down...");
     client.disconnect().await;
+    copier.report_paper().await;
+    if trading_mode != TradingMode::Off {
+        copier.report_targets().await;
+        copier.report_execution();
+    }
 
     Ok(())
 }
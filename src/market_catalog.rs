@@ -0,0 +1,315 @@
+use chrono::{DateTime, Utc};
+use polymarket_client_sdk::clob::{Client, Config};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::env_parse;
+use crate::gamma::GammaClient;
+
+const DEFAULT_CLOB_HOST: &str = "https://clob.polymarket.com";
+const DEFAULT_CACHE_TTL_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Outcome {
+    pub token_id: String,
+    pub name: String,
+    /// Last price reported by the CLOB; `1`/`0` once the market has resolved.
+    pub price: Decimal,
+    pub winner: bool,
+}
+
+/// Market context the trade payload lacks, merged from the CLOB and Gamma APIs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketInfo {
+    pub condition_id: String,
+    pub question: String,
+    pub slug: Option<String>,
+    pub event_slug: Option<String>,
+    pub tags: Vec<String>,
+    pub end_date: Option<DateTime<Utc>>,
+    pub active: bool,
+    pub closed: bool,
+    pub accepting_orders: bool,
+    pub neg_risk: bool,
+    pub tick_size: Decimal,
+    pub min_order_size: Decimal,
+    pub liquidity: Option<Decimal>,
+    pub volume_24hr: Option<Decimal>,
+    pub outcomes: Vec<Outcome>,
+}
+
+impl MarketInfo {
+    /// Whether the CLOB will currently accept orders for this market.
+    pub fn is_tradable(&self) -> bool {
+        self.active && !self.closed && self.accepting_orders
+    }
+
+    /// Whether the market is closed with a winning outcome reported.
+    pub fn is_resolved(&self) -> bool {
+        self.closed && self.outcomes.iter().any(|o| o.winner)
+    }
+
+    /// Payout per share of `token_id` once the market has resolved, `None` while it is open.
+    pub fn resolution_price(&self, token_id: &str) -> Option<Decimal> {
+        if !self.is_resolved() {
+            return None;
+        }
+        let outcome = self.outcomes.iter().find(|o| o.token_id == token_id)?;
+        Some(if outcome.winner {
+            Decimal::ONE
+        } else {
+            Decimal::ZERO
+        })
+    }
+
+    /// Last known price of `token_id`.
+    pub fn last_price(&self, token_id: &str) -> Option<Decimal> {
+        self.outcomes
+            .iter()
+            .find(|o| o.token_id == token_id)
+            .map(|o| o.price)
+    }
+}
+
+type Cached = (Instant, Arc<MarketInfo>);
+
+#[derive(Default)]
+struct CatalogState {
+    by_condition: HashMap<String, Cached>,
+    condition_by_token: HashMap<String, String>,
+    in_flight: HashSet<String>,
+}
+
+/// TTL cache of [`MarketInfo`] keyed by condition id and token id.
+///
+/// Lookups return cached entries immediately, refreshing stale ones in the background, so the
+/// copy path only waits on the network the first time a market is seen.
+#[derive(Clone)]
+pub struct MarketCatalog {
+    clob: Client,
+    gamma: GammaClient,
+    ttl: Duration,
+    state: Arc<Mutex<CatalogState>>,
+}
+
+impl MarketCatalog {
+    pub fn new(clob: Client, gamma: GammaClient, ttl: Duration) -> Self {
+        Self {
+            clob,
+            gamma,
+            ttl,
+            state: Arc::new(Mutex::new(CatalogState::default())),
+        }
+    }
+
+    /// Reads `CLOB_HOST`, `GAMMA_HOST` and `MARKET_CACHE_TTL_SECS`.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let host = env::var("CLOB_HOST").unwrap_or_else(|_| DEFAULT_CLOB_HOST.to_string());
+        let ttl_secs = env_parse("MARKET_CACHE_TTL_SECS", DEFAULT_CACHE_TTL_SECS)?;
+        Ok(Self::new(
+            Client::new(&host, Config::default())?,
+            GammaClient::from_env(),
+            Duration::from_secs(ttl_secs),
+        ))
+    }
+
+    /// Cached entry for `condition_id`, fresh or stale, without touching the network.
+    pub fn cached(&self, condition_id: &str) -> Option<Arc<MarketInfo>> {
+        let state = self.state.lock().unwrap();
+        state
+            .by_condition
+            .get(condition_id)
+            .map(|(_, info)| Arc::clone(info))
+    }
+
+    /// Cached entry for the market that owns `token_id`, without touching the network.
+    pub fn cached_by_token(&self, token_id: &str) -> Option<Arc<MarketInfo>> {
+        let state = self.state.lock().unwrap();
+        let condition_id = state.condition_by_token.get(token_id)?;
+        state
+            .by_condition
+            .get(condition_id)
+            .map(|(_, info)| Arc::clone(info))
+    }
+
+    /// Starts a background fetch for `condition_id` if it is missing or stale and no fetch is
+    /// already running.
+    pub fn warm(&self, condition_id: &str) {
+        {
+            let mut state = self.state.lock().unwrap();
+            let fresh = state
+                .by_condition
+                .get(condition_id)
+                .is_some_and(|(fetched_at, _)| fetched_at.elapsed() < self.ttl);
+            if fresh || !state.in_flight.insert(condition_id.to_string()) {
+                return;
+            }
+        }
+
+        let catalog = self.clone();
+        let condition_id = condition_id.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = catalog.refresh(&condition_id).await {
+                eprintln!("Error loading market {}: {}", condition_id, e);
+            }
+            catalog
+                .state
+                .lock()
+                .unwrap()
+                .in_flight
+                .remove(&condition_id);
+        });
+    }
+
+    /// Returns the market for `condition_id`. Cached entries are returned immediately (a stale one
+    /// also triggers a background refresh); only a market never seen before waits on the network.
+    pub async fn market(
+        &self,
+        condition_id: &str,
+    ) -> Result<Arc<MarketInfo>, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(info) = self.cached(condition_id) {
+            self.warm(condition_id);
+            return Ok(info);
+        }
+        self.refresh(condition_id).await
+    }
+
+    /// Returns the market that owns `token_id`, resolving the condition id through Gamma when the
+    /// token has not been seen before.
+    pub async fn market_by_token(
+        &self,
+        token_id: &str,
+    ) -> Result<Arc<MarketInfo>, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(info) = self.cached_by_token(token_id) {
+            self.warm(&info.condition_id);
+            return Ok(info);
+        }
+        let gamma_market = self.gamma.market_by_token(token_id).await?;
+        self.market(&gamma_market.condition_id).await
+    }
+
+    /// Fetches CLOB and Gamma metadata for `condition_id` and stores the merged result.
+    pub async fn refresh(
+        &self,
+        condition_id: &str,
+    ) -> Result<Arc<MarketInfo>, Box<dyn std::error::Error + Send + Sync>> {
+        let (clob_market, gamma_market) = tokio::join!(
+            self.clob.market(condition_id),
+            self.gamma.market(condition_id)
+        );
+        let clob_market = clob_market?;
+        let gamma_market = gamma_market?;
+
+        let info = Arc::new(MarketInfo {
+            condition_id: clob_market.condition_id.clone(),
+            question: clob_market.question.clone(),
+            slug: gamma_market
+                .slug
+                .clone()
+                .or_else(|| Some(clob_market.market_slug.clone()).filter(|s| !s.is_empty())),
+            event_slug: gamma_market.event_slug().map(str::to_string),
+            tags: gamma_market.tag_names(),
+            end_date: gamma_market.end_date.or(clob_market.end_date_iso),
+            active: clob_market.active,
+            closed: clob_market.closed,
+            accepting_orders: clob_market.accepting_orders,
+            neg_risk: clob_market.neg_risk,
+            tick_size: clob_market.minimum_tick_size,
+            min_order_size: clob_market.minimum_order_size,
+            liquidity: gamma_market.liquidity,
+            volume_24hr: gamma_market.volume_24hr,
+            outcomes: clob_market
+                .tokens
+                .iter()
+                .map(|t| Outcome {
+                    token_id: t.token_id.clone(),
+                    name: t.outcome.clone(),
+                    price: t.price,
+                    winner: t.winner,
+                })
+                .collect(),
+        });
+
+        let mut state = self.state.lock().unwrap();
+        let is_new = !state.by_condition.contains_key(condition_id);
+        for outcome in &info.outcomes {
+            state
+                .condition_by_token
+                .insert(outcome.token_id.clone(), condition_id.to_string());
+        }
+        state.by_condition.insert(
+            condition_id.to_string(),
+            (Instant::now(), Arc::clone(&info)),
+        );
+        drop(state);
+
+        if is_new {
+            let outcomes = info
+                .outcomes
+                .iter()
+                .map(|o| o.name.as_str())
+                .collect::<Vec<_>>()
+                .join("/");
+            println!(
+                "Loaded market {} ({}): outcomes {}, tick {}, min size {}, neg risk {}",
+                info.question,
+                condition_id,
+                outcomes,
+                info.tick_size,
+                info.min_order_size,
+                info.neg_risk
+            );
+        }
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    /// An open binary market with a 0.01 tick and a 5 share minimum.
+    pub(crate) fn market() -> MarketInfo {
+        let outcome = |token_id: &str, name: &str| Outcome {
+            token_id: token_id.to_string(),
+            name: name.to_string(),
+            price: dec!(0.5),
+            winner: false,
+        };
+        MarketInfo {
+            condition_id: "0xcondition".to_string(),
+            question: "Will it happen?".to_string(),
+            slug: Some("will-it-happen".to_string()),
+            event_slug: Some("happenings".to_string()),
+            tags: vec!["politics".to_string()],
+            end_date: DateTime::from_timestamp(1_000_000, 0),
+            active: true,
+            closed: false,
+            accepting_orders: true,
+            neg_risk: false,
+            tick_size: dec!(0.01),
+            min_order_size: dec!(5),
+            liquidity: Some(dec!(1000)),
+            volume_24hr: Some(dec!(5000)),
+            outcomes: vec![outcome("1", "Yes"), outcome("2", "No")],
+        }
+    }
+
+    #[test]
+    fn resolution_price_only_once_resolved() {
+        let mut market = market();
+        assert!(market.is_tradable());
+        assert_eq!(market.resolution_price("1"), None);
+
+        market.closed = true;
+        market.outcomes[0].winner = true;
+        assert!(market.is_resolved());
+        assert_eq!(market.resolution_price("1"), Some(Decimal::ONE));
+        assert_eq!(market.resolution_price("2"), Some(Decimal::ZERO));
+    }
+}
@@ -0,0 +1,186 @@
+use alloy::providers::Provider;
+use polymarket_client_sdk::ContractConfig;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::collections::HashSet;
+use std::str::FromStr as _;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::account::AccountState;
+use crate::config::{env_opt, env_parse};
+use crate::funder::Funder;
+use crate::notifier::Notifier;
+use crate::util::{format_balance, spenders, IERC20, USDC_DECIMALS};
+
+const POL_DECIMALS: u8 = 18;
+
+/// Name of the check that pauses buying.
+const FLOOR_CHECK: &str = "USDC balance (collateral floor)";
+
+/// Threshold `name`, `default` when unset; 0 disables its check.
+fn threshold(
+    name: &str,
+    default: Option<Decimal>,
+) -> Result<Option<Decimal>, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(env_opt(name)?
+        .or(default)
+        .filter(|value: &Decimal| !value.is_zero()))
+}
+
+/// Thresholds for the wallet balance monitor. `None` disables a check.
+#[derive(Debug, Clone)]
+pub struct MonitorConfig {
+    /// Time between checks; zero disables the monitor.
+    pub interval: Duration,
+    /// Alert when the wallet's USDC falls below this.
+    pub min_usdc: Option<Decimal>,
+    /// Alert when the POL left for gas falls below this.
+    pub min_pol: Option<Decimal>,
+    /// Alert when a spender's USDC allowance falls below this.
+    pub min_allowance: Option<Decimal>,
+    /// Pause buying while the wallet's USDC is below this.
+    pub collateral_floor: Option<Decimal>,
+}
+
+impl MonitorConfig {
+    /// Reads `BALANCE_MONITOR_SECS` (default 300), `ALERT_MIN_USDC` (default 10),
+    /// `ALERT_MIN_POL` (default 1), `ALERT_MIN_ALLOWANCE_USDC` (default 50) and
+    /// `COLLATERAL_FLOOR_USDC` (unset by default). A threshold of 0 disables its check.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self {
+            interval: Duration::from_secs(env_parse("BALANCE_MONITOR_SECS", 300)?),
+            min_usdc: threshold("ALERT_MIN_USDC", Some(dec!(10)))?,
+            min_pol: threshold("ALERT_MIN_POL", Some(Decimal::ONE))?,
+            min_allowance: threshold("ALERT_MIN_ALLOWANCE_USDC", Some(dec!(50)))?,
+            collateral_floor: threshold("COLLATERAL_FLOOR_USDC", None)?,
+        })
+    }
+}
+
+/// One balance checked against its threshold.
+struct Reading {
+    /// Also the key that tracks whether its alert is raised.
+    name: String,
+    value: Decimal,
+    min: Decimal,
+    /// Appended to the low alert.
+    consequence: &'static str,
+}
+
+impl Reading {
+    fn is_low(&self) -> bool {
+        self.value < self.min
+    }
+}
+
+/// Reads every balance that has a threshold once.
+async fn read_balances<P: Provider>(
+    provider: &P,
+    funder: &Funder,
+    config: &ContractConfig,
+    neg_risk_config: &ContractConfig,
+    thresholds: &MonitorConfig,
+) -> Result<Vec<Reading>, Box<dyn std::error::Error + Send + Sync>> {
+    // Unlimited allowances overflow a Decimal; anything that large is never low.
+    let whole = |units, decimals| {
+        Decimal::from_str(&format_balance(units, decimals)).unwrap_or(Decimal::MAX)
+    };
+    let usdc = IERC20::new(config.collateral, provider);
+    let owner = funder.address();
+    let balance = whole(usdc.balanceOf(owner).call().await?, USDC_DECIMALS).round_dp(2);
+    // Gas is paid by the key's EOA, also when the funds sit in a proxy wallet or Safe.
+    let pol = whole(
+        provider.get_balance(funder.signer_address()).await?,
+        POL_DECIMALS,
+    )
+    .round_dp(4);
+
+    let mut readings = Vec::new();
+    if let Some(min) = thresholds.min_usdc {
+        readings.push(Reading {
+            name: "USDC balance".to_string(),
+            value: balance,
+            min,
+            consequence: "",
+        });
+    }
+    if let Some(min) = thresholds.min_pol {
+        readings.push(Reading {
+            name: "POL balance".to_string(),
+            value: pol,
+            min,
+            consequence: "; transactions may run out of gas",
+        });
+    }
+    if let Some(min) = thresholds.min_allowance {
+        for spender in spenders(config, neg_risk_config) {
+            let token = IERC20::new(spender.collateral, provider);
+            let allowance = token.allowance(owner, spender.address).call().await?;
+            readings.push(Reading {
+                name: format!("USDC allowance of {}", spender.name),
+                value: whole(allowance, USDC_DECIMALS).round_dp(2),
+                min,
+                consequence: "; orders through it may be rejected",
+            });
+        }
+    }
+    if let Some(min) = thresholds.collateral_floor {
+        readings.push(Reading {
+            name: FLOOR_CHECK.to_string(),
+            value: balance,
+            min,
+            consequence: "; buying is paused",
+        });
+    }
+    Ok(readings)
+}
+
+/// Watches the wallet's USDC, its POL for gas and each spender's USDC allowance every
+/// `thresholds.interval`, notifying when one drops below its threshold and again when it
+/// recovers. While the USDC balance is below the collateral floor, `account` refuses new buys.
+pub async fn run_balance_monitor<P: Provider>(
+    provider: P,
+    funder: Funder,
+    config: &ContractConfig,
+    neg_risk_config: &ContractConfig,
+    thresholds: MonitorConfig,
+    notifier: Notifier,
+    account: Arc<AccountState>,
+) {
+    // Checks whose low alert has been sent and not yet cleared.
+    let mut raised: HashSet<String> = HashSet::new();
+    let mut ticker = tokio::time::interval(thresholds.interval);
+    loop {
+        ticker.tick().await;
+        let readings =
+            match read_balances(&provider, &funder, config, neg_risk_config, &thresholds).await {
+                Ok(readings) => readings,
+                Err(e) => {
+                    eprintln!("Error checking wallet balances: {}", e);
+                    continue;
+                }
+            };
+        for reading in readings {
+            if reading.name == FLOOR_CHECK {
+                account.pause_buying(reading.is_low().then(|| {
+                    format!(
+                        "USDC balance {} is below the {} floor",
+                        reading.value, reading.min
+                    )
+                }));
+            }
+            if reading.is_low() && raised.insert(reading.name.clone()) {
+                notifier.notify(&format!(
+                    "{} is {}, below {}{}",
+                    reading.name, reading.value, reading.min, reading.consequence
+                ));
+            } else if !reading.is_low() && raised.remove(&reading.name) {
+                notifier.notify(&format!(
+                    "{} is back to {}, at or above {}",
+                    reading.name, reading.value, reading.min
+                ));
+            }
+        }
+    }
+}
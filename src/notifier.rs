@@ -0,0 +1,41 @@
+use std::env;
+
+/// Operator alerts. Every message is logged; when `NOTIFY_WEBHOOK_URL` is set it is also posted
+/// there as JSON with `text` and `content` fields, which Slack and Discord webhooks both accept.
+#[derive(Clone)]
+pub struct Notifier {
+    http: reqwest::Client,
+    webhook_url: Option<String>,
+}
+
+impl Notifier {
+    pub fn from_env() -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            webhook_url: env::var("NOTIFY_WEBHOOK_URL")
+                .ok()
+                .filter(|url| !url.is_empty()),
+        }
+    }
+
+    /// Logs `message` and posts it to the webhook in the background.
+    pub fn notify(&self, message: &str) {
+        println!("[notify] {}", message);
+        let Some(url) = self.webhook_url.clone() else {
+            return;
+        };
+        let http = self.http.clone();
+        let body = serde_json::json!({ "text": message, "content": message });
+        tokio::spawn(async move {
+            let result = http
+                .post(&url)
+                .json(&body)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(e) = result {
+                eprintln!("Error posting notification: {}", e);
+            }
+        });
+    }
+}
@@ -0,0 +1,874 @@
+use alloy::primitives::U256;
+use alloy::sol_types::{Eip712Domain, SolStruct as _};
+use chrono::{DateTime, Utc};
+use polymarket_client_sdk::error::Error as SdkError;
+use polymarket_client_sdk::types::{
+    Amount, Order, OrderType, PostOrderResponse, PostOrderResponseBuilder, Side, SignableOrder,
+    SignedOrder, SignedOrderBuilder,
+};
+use polymarket_client_sdk::{contract_config, POLYGON};
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::config::env_parse;
+use crate::copier::{ClobClient, SharedClient};
+use crate::execution_error::ExecutionError;
+use crate::funder::Funder;
+use crate::notifier::Notifier;
+use crate::store::Store;
+
+const DEFAULT_CLOB_HOST: &str = "https://clob.polymarket.com";
+
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const DEFAULT_RETRY_BASE_MS: u64 = 250;
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(4);
+const DEFAULT_FOLLOW_UP_DELAY_MS: u64 = 500;
+const DEFAULT_BALANCE_PAUSE_SECS: u64 = 300;
+/// Store counter holding the balance pause as `<until, RFC 3339> <reason>`.
+const BALANCE_PAUSE_COUNTER: &str = "balance_pause";
+/// Prefix of the store counters holding error counts by class.
+const ERROR_COUNTER_PREFIX: &str = "errors.";
+
+/// Where a copy intent is in its lifecycle. `Done` and `Failed` are terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderState {
+    Intent,
+    Built,
+    Signed,
+    Posted,
+    PartiallyFilled,
+    Filled,
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderStage {
+    Build,
+    Sign,
+    Post,
+}
+
+impl fmt::Display for OrderStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderStage::Build => write!(f, "build"),
+            OrderStage::Sign => write!(f, "sign"),
+            OrderStage::Post => write!(f, "post"),
+        }
+    }
+}
+
+/// Why an intent ended in [`OrderState::Failed`], or why it stopped short of a full fill.
+#[derive(Debug, Clone)]
+pub struct OrderFailure {
+    pub stage: OrderStage,
+    pub attempts: u32,
+    pub error: ExecutionError,
+}
+
+impl fmt::Display for OrderFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} failed after {} attempt(s){}: {}",
+            self.stage,
+            self.attempts,
+            if self.error.is_transient() {
+                ", retries exhausted"
+            } else {
+                ""
+            },
+            self.error
+        )
+    }
+}
+
+/// Limit price implied by a built buy order: USDC given up per share received.
+pub fn limit_price(order: &SignableOrder) -> Option<Decimal> {
+    let maker = Decimal::from_str(&order.order.makerAmount.to_string()).ok()?;
+    let taker = Decimal::from_str(&order.order.takerAmount.to_string()).ok()?;
+    (!taker.is_zero()).then(|| maker / taker)
+}
+
+/// Whether a follow-up order's `limit` is at or inside the first order's. Refused when either
+/// limit cannot be read.
+fn within_first_limit(limit: Option<Decimal>, first: Option<Decimal>) -> bool {
+    match (limit, first) {
+        (Some(limit), Some(first)) => limit <= first,
+        _ => false,
+    }
+}
+
+/// A byte-identical copy of `order`, so a post retried after a timeout carries the same salt and
+/// signature and cannot be filled twice.
+fn duplicate(order: &SignedOrder) -> Result<SignedOrder, SdkError> {
+    SignedOrderBuilder::default()
+        .order(order.order.clone())
+        .signature(order.signature)
+        .order_type(order.order_type)
+        .owner(order.owner)
+        .build()
+}
+
+/// The exchange's id for `order`: its EIP-712 hash under the exchange contract of the token's
+/// market type, as signed by [`ClobClient::sign`].
+async fn order_id(
+    client: &ClobClient,
+    chain_id: Option<u64>,
+    order: &Order,
+) -> Result<String, SdkError> {
+    let neg_risk = client.neg_risk(&order.tokenId.to_string()).await?.neg_risk;
+    let chain_id = chain_id.unwrap_or(POLYGON);
+    let exchange = contract_config(chain_id, neg_risk)
+        .ok_or_else(|| {
+            SdkError::validation(format!(
+                "No exchange contract for chain {} (neg risk {})",
+                chain_id, neg_risk
+            ))
+        })?
+        .exchange;
+    let domain = Eip712Domain {
+        name: Some("Polymarket CTF Exchange".into()),
+        version: Some("1".into()),
+        chain_id: Some(U256::from(chain_id)),
+        verifying_contract: Some(exchange),
+        ..Eip712Domain::default()
+    };
+    Ok(order.eip712_signing_hash(&domain).to_string())
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderConfig {
+    pub max_retries: u32,
+    pub retry_base: Duration,
+    /// Extra FAK orders sent for the unfilled remainder of a partial fill.
+    pub max_follow_ups: u32,
+    pub follow_up_delay: Duration,
+    /// How long live copying stops after a balance or allowance rejection.
+    pub balance_pause: Duration,
+    /// CLOB host used to re-authenticate when the API credentials are rejected.
+    pub host: String,
+}
+
+impl OrderConfig {
+    /// Reads `ORDER_MAX_RETRIES` (default 3), `ORDER_RETRY_BASE_MS` (default 250),
+    /// `ORDER_MAX_FOLLOW_UPS` (default 0), `ORDER_FOLLOW_UP_DELAY_MS` (default 500),
+    /// `ORDER_BALANCE_PAUSE_SECS` (default 300) and `CLOB_HOST`.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self {
+            max_retries: env_parse("ORDER_MAX_RETRIES", DEFAULT_MAX_RETRIES)?,
+            retry_base: Duration::from_millis(env_parse(
+                "ORDER_RETRY_BASE_MS",
+                DEFAULT_RETRY_BASE_MS,
+            )?),
+            max_follow_ups: env_parse("ORDER_MAX_FOLLOW_UPS", 0)?,
+            follow_up_delay: Duration::from_millis(env_parse(
+                "ORDER_FOLLOW_UP_DELAY_MS",
+                DEFAULT_FOLLOW_UP_DELAY_MS,
+            )?),
+            balance_pause: Duration::from_secs(env_parse(
+                "ORDER_BALANCE_PAUSE_SECS",
+                DEFAULT_BALANCE_PAUSE_SECS,
+            )?),
+            host: env::var("CLOB_HOST").unwrap_or_else(|_| DEFAULT_CLOB_HOST.to_string()),
+        })
+    }
+}
+
+/// One copy order and its progress.
+#[derive(Debug, Clone)]
+pub struct OrderIntent {
+    pub id: u64,
+    pub target: String,
+    pub token_id: String,
+    pub requested: Decimal,
+    pub spent: Decimal,
+    pub shares: Decimal,
+    pub state: OrderState,
+    pub order_ids: Vec<String>,
+    pub failure: Option<OrderFailure>,
+}
+
+impl OrderIntent {
+    fn transition(&mut self, state: OrderState) {
+        self.state = state;
+        println!(
+            "Order #{} for {} copying {}: {:?} (filled {} of {} USDC)",
+            self.id,
+            self.token_id,
+            self.target,
+            state,
+            self.spent.round_dp(4),
+            self.requested
+        );
+    }
+
+    /// Records `failure`. The intent fails only if nothing has filled yet; a follow-up that fails
+    /// leaves the earlier fills in place.
+    fn fail(&mut self, failure: OrderFailure) {
+        if self.spent.is_zero() {
+            eprintln!(
+                "Order #{} for {} failed: {}",
+                self.id, self.token_id, failure
+            );
+            self.failure = Some(failure);
+            self.transition(OrderState::Failed);
+        } else {
+            eprintln!("Follow-up for order #{} failed: {}", self.id, failure);
+            self.failure = Some(failure);
+        }
+    }
+}
+
+/// Drives copy orders through build, sign and post with retries on transient errors, and
+/// follows up FAK partial fills within [`OrderConfig`] limits.
+///
+/// Every rejection is classified as an [`ExecutionError`] and counted per class. Rejected
+/// credentials trigger one re-authentication and retry; a balance or allowance rejection pauses
+/// live copying for [`OrderConfig::balance_pause`]. Intents, their orders, the counts and the
+/// pause are kept in the [`Store`].
+pub struct OrderManager {
+    /// Shared with the [`AccountState`](crate::account::AccountState), so it also uses the
+    /// credentials this renews.
+    client: SharedClient,
+    funder: Funder,
+    config: OrderConfig,
+    notifier: Notifier,
+    store: Arc<Store>,
+    next_id: AtomicU64,
+    errors: Mutex<BTreeMap<String, u64>>,
+    paused: Mutex<Option<(DateTime<Utc>, String)>>,
+    reauth: tokio::sync::Mutex<()>,
+}
+
+impl OrderManager {
+    pub fn new(
+        client: SharedClient,
+        funder: Funder,
+        config: OrderConfig,
+        notifier: Notifier,
+        store: Arc<Store>,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        // Whether an order posted before a crash filled is unknown; its trade is already marked
+        // processed, so it is not copied again.
+        for (id, token_id, state) in store.interrupt_unfinished_intents()? {
+            eprintln!(
+                "Order #{} for {} was interrupted at {} by a restart{}",
+                id,
+                token_id,
+                state,
+                if state == format!("{:?}", OrderState::Intent)
+                    || state == format!("{:?}", OrderState::Built)
+                    || state == format!("{:?}", OrderState::Signed)
+                {
+                    ""
+                } else {
+                    "; it may have filled, check the exchange"
+                }
+            );
+        }
+        let errors = store
+            .counters(ERROR_COUNTER_PREFIX)?
+            .into_iter()
+            .filter_map(|(kind, count)| Some((kind, count.parse::<u64>().ok()?)))
+            .collect();
+        let paused = store.counter(BALANCE_PAUSE_COUNTER)?.and_then(|value| {
+            let (until, reason) = value.split_once(' ')?;
+            let until = DateTime::parse_from_rfc3339(until).ok()?;
+            Some((until.with_timezone(&Utc), reason.to_string()))
+        });
+
+        Ok(Self {
+            client,
+            funder,
+            config,
+            notifier,
+            next_id: AtomicU64::new(store.last_intent_id()? + 1),
+            store,
+            errors: Mutex::new(errors),
+            paused: Mutex::new(paused),
+            reauth: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    fn client(&self) -> (ClobClient, u64) {
+        self.client.current()
+    }
+
+    /// Count of every classified error, retried ones included.
+    pub fn error_counts(&self) -> BTreeMap<String, u64> {
+        self.errors.lock().unwrap().clone()
+    }
+
+    fn count_error(&self, error: &ExecutionError) {
+        let mut errors = self.errors.lock().unwrap();
+        let count = errors.entry(error.kind().to_string()).or_default();
+        *count += 1;
+        let name = format!("{}{}", ERROR_COUNTER_PREFIX, error.kind());
+        if let Err(e) = self.store.set_counter(&name, &count.to_string()) {
+            eprintln!("Error saving {} counter: {}", name, e);
+        }
+    }
+
+    fn save(&self, intent: &OrderIntent, responses: &[PostOrderResponse]) {
+        if let Err(e) = self.store.save_intent(intent, responses) {
+            eprintln!("Error saving order #{}: {}", intent.id, e);
+        }
+    }
+
+    /// Why live copying is paused, while it is.
+    pub fn paused(&self) -> Option<String> {
+        let mut paused = self.paused.lock().unwrap();
+        match &*paused {
+            Some((until, reason)) if Utc::now() < *until => Some(reason.clone()),
+            Some(_) => {
+                *paused = None;
+                if let Err(e) = self.store.clear_counter(BALANCE_PAUSE_COUNTER) {
+                    eprintln!("Error clearing balance pause: {}", e);
+                }
+                println!("Resuming live copying after balance pause");
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Derives fresh API credentials and swaps in a new authenticated client. Callers that
+    /// raced on the same rejection wait for the first re-authentication instead of repeating it.
+    async fn reauthenticate(&self, stale_generation: u64) -> Result<(), SdkError> {
+        let _guard = self.reauth.lock().await;
+        if self.client().1 != stale_generation {
+            return Ok(());
+        }
+        println!("API credentials rejected, re-authenticating");
+        let client = self.funder.authenticate(&self.config.host).await?;
+        self.client.replace(client);
+        Ok(())
+    }
+
+    /// Reacts to the error that ended or cut short an intent.
+    fn react(&self, error: &ExecutionError) {
+        if let ExecutionError::InsufficientBalance(message) = error {
+            let until = Utc::now()
+                + chrono::Duration::from_std(self.config.balance_pause).unwrap_or_default();
+            let reason = error.to_string();
+            if let Err(e) = self.store.set_counter(
+                BALANCE_PAUSE_COUNTER,
+                &format!("{} {}", until.to_rfc3339(), reason),
+            ) {
+                eprintln!("Error saving balance pause: {}", e);
+            }
+            *self.paused.lock().unwrap() = Some((until, reason));
+            self.notifier.notify(&format!(
+                "Pausing live copying for {:?}: insufficient balance or allowance ({})",
+                self.config.balance_pause, message
+            ));
+        }
+    }
+
+    /// Runs `call` until it succeeds, fails with a non-transient error or runs out of retries,
+    /// backing off exponentially between attempts. `call` gets the current client, so a retry
+    /// after re-authentication uses the new credentials.
+    async fn retry<T, F, Fut>(&self, stage: OrderStage, mut call: F) -> Result<T, OrderFailure>
+    where
+        F: FnMut(ClobClient) -> Fut,
+        Fut: Future<Output = Result<T, SdkError>>,
+    {
+        let mut attempts = 0;
+        let mut reauthenticated = false;
+        loop {
+            attempts += 1;
+            let (client, generation) = self.client();
+            let error = match call(client).await {
+                Ok(value) => return Ok(value),
+                Err(e) => ExecutionError::from_sdk(&e),
+            };
+            self.count_error(&error);
+
+            if matches!(error, ExecutionError::AuthExpired(_)) && !reauthenticated {
+                reauthenticated = true;
+                match self.reauthenticate(generation).await {
+                    Ok(()) => continue,
+                    Err(e) => eprintln!("Error re-authenticating: {}", e),
+                }
+            }
+            if !error.is_transient() || attempts > self.config.max_retries {
+                return Err(OrderFailure {
+                    stage,
+                    attempts,
+                    error,
+                });
+            }
+            let delay =
+                (self.config.retry_base * 2u32.saturating_pow(attempts - 1)).min(MAX_RETRY_DELAY);
+            eprintln!(
+                "Retrying order {} in {:?} after attempt {}: {}",
+                stage, delay, attempts, error
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Builds a FAK market buy for `amount` USDC of `token_id`.
+    pub async fn build(
+        &self,
+        token_id: &str,
+        amount: Decimal,
+    ) -> Result<SignableOrder, OrderFailure> {
+        self.retry(OrderStage::Build, |client| async move {
+            client
+                .market_order()
+                .token_id(token_id)
+                .order_type(OrderType::FAK)
+                .amount(Amount::usdc(amount)?)
+                .side(Side::Buy)
+                .build()
+                .await
+        })
+        .await
+    }
+
+    pub async fn sign(&self, order: SignableOrder) -> Result<SignedOrder, OrderFailure> {
+        self.retry(OrderStage::Sign, |client| {
+            let order = order.clone();
+            async move { client.sign(self.funder.signer(), order).await }
+        })
+        .await
+    }
+
+    /// Posts `order`. A post that still fails after a retry may have reached the exchange on an
+    /// earlier attempt, in which case the retry is rejected as a duplicate, so the order is looked
+    /// up by its id and any fill it got is returned instead of the failure.
+    async fn post(&self, order: &SignedOrder) -> Result<Vec<PostOrderResponse>, OrderFailure> {
+        let failure = match self
+            .retry(OrderStage::Post, |client| async move {
+                client.post_order(duplicate(order)?).await
+            })
+            .await
+        {
+            Ok(responses) => return Ok(responses),
+            Err(failure) if failure.attempts == 1 => return Err(failure),
+            Err(failure) => failure,
+        };
+        match self.posted_fill(&order.order).await {
+            Ok(Some(response)) => {
+                eprintln!(
+                    "Order {} was posted before {}; it matched {} shares",
+                    response.order_id, failure, response.taking_amount
+                );
+                Ok(vec![response])
+            }
+            Ok(None) => Err(failure),
+            Err(e) => {
+                eprintln!("Error looking up order after {}: {}", failure, e);
+                Err(failure)
+            }
+        }
+    }
+
+    /// What the exchange matched of `order`, as a post response, or `None` if it matched
+    /// nothing. The USDC spent is taken at the order's limit price, which bounds what the fill
+    /// cost.
+    async fn posted_fill(&self, order: &Order) -> Result<Option<PostOrderResponse>, SdkError> {
+        let (client, _) = self.client();
+        let order_id = order_id(&client, self.funder.signer().chain_id(), order).await?;
+        let posted = client.order(&order_id).await?;
+        if posted.size_matched.is_zero() {
+            return Ok(None);
+        }
+        PostOrderResponseBuilder::default()
+            .making_amount(posted.size_matched * posted.price)
+            .taking_amount(posted.size_matched)
+            .order_id(posted.id)
+            .status(posted.status)
+            .success(true)
+            .build()
+            .map(Some)
+    }
+
+    /// Buys `amount` USDC of `token_id` for `target`. When a FAK order fills only partially and
+    /// follow-ups are enabled, the remainder is re-sent as long as it is at least `min_amount` and
+    /// the new order's limit price is no worse than the first one's.
+    pub async fn execute(
+        &self,
+        target: &str,
+        token_id: &str,
+        amount: Decimal,
+        min_amount: Decimal,
+    ) -> OrderIntent {
+        let mut intent = OrderIntent {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            target: target.to_string(),
+            token_id: token_id.to_string(),
+            requested: amount,
+            spent: Decimal::ZERO,
+            shares: Decimal::ZERO,
+            state: OrderState::Intent,
+            order_ids: Vec::new(),
+            failure: None,
+        };
+        intent.transition(OrderState::Intent);
+        self.save(&intent, &[]);
+
+        self.run(&mut intent, min_amount).await;
+        if intent.state != OrderState::Failed {
+            intent.transition(OrderState::Done);
+        }
+        self.save(&intent, &[]);
+        if let Some(failure) = &intent.failure {
+            self.react(&failure.error);
+        }
+        intent
+    }
+
+    async fn run(&self, intent: &mut OrderIntent, min_amount: Decimal) {
+        let mut remaining = intent.requested;
+        let mut first_limit = None;
+        let mut follow_ups = 0;
+        loop {
+            let order = match self.build(&intent.token_id, remaining).await {
+                Ok(order) => order,
+                Err(failure) => return intent.fail(failure),
+            };
+            let limit = limit_price(&order);
+            if follow_ups == 0 {
+                first_limit = limit;
+            } else if !within_first_limit(limit, first_limit) {
+                println!(
+                    "Follow-up for order #{} not sent: limit {:?} is not within {:?}",
+                    intent.id, limit, first_limit
+                );
+                return;
+            }
+            intent.transition(OrderState::Built);
+
+            let signed = match self.sign(order).await {
+                Ok(signed) => signed,
+                Err(failure) => return intent.fail(failure),
+            };
+            intent.transition(OrderState::Signed);
+
+            let responses = match self.post(&signed).await {
+                Ok(responses) => responses,
+                Err(failure) => return intent.fail(failure),
+            };
+            intent.transition(OrderState::Posted);
+            self.save(intent, &responses);
+
+            // For a buy the maker side is USDC and the taker side is shares.
+            let mut filled = false;
+            for response in &responses {
+                if !response.success {
+                    continue;
+                }
+                intent.order_ids.push(response.order_id.clone());
+                intent.spent += response.making_amount;
+                intent.shares += response.taking_amount;
+                filled |= !response.taking_amount.is_zero();
+            }
+            if !filled {
+                let error = responses
+                    .iter()
+                    .find_map(|r| r.error_msg.as_deref().filter(|m| !m.is_empty()))
+                    .map_or_else(
+                        || ExecutionError::NoLiquidity("no fill".to_string()),
+                        ExecutionError::from_message,
+                    );
+                self.count_error(&error);
+                return intent.fail(OrderFailure {
+                    stage: OrderStage::Post,
+                    attempts: 1,
+                    error,
+                });
+            }
+
+            remaining = (intent.requested - intent.spent)
+                .round_dp_with_strategy(2, RoundingStrategy::ToZero)
+                .max(Decimal::ZERO);
+            if remaining.is_zero() {
+                return intent.transition(OrderState::Filled);
+            }
+            intent.transition(OrderState::PartiallyFilled);
+            if follow_ups >= self.config.max_follow_ups || remaining < min_amount {
+                return;
+            }
+            follow_ups += 1;
+            tokio::time::sleep(self.config.follow_up_delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::AccountState;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::Signer as _;
+    use polymarket_client_sdk::types::SignatureType;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn follow_up_only_within_first_limit() {
+        assert!(within_first_limit(Some(dec!(0.5)), Some(dec!(0.5))));
+        assert!(within_first_limit(Some(dec!(0.49)), Some(dec!(0.5))));
+        assert!(!within_first_limit(Some(dec!(0.51)), Some(dec!(0.5))));
+        assert!(!within_first_limit(None, Some(dec!(0.5))));
+        assert!(!within_first_limit(Some(dec!(0.5)), None));
+    }
+
+    fn intent() -> OrderIntent {
+        OrderIntent {
+            id: 1,
+            target: "0xtarget".to_string(),
+            token_id: "1".to_string(),
+            requested: dec!(10),
+            spent: Decimal::ZERO,
+            shares: Decimal::ZERO,
+            state: OrderState::Posted,
+            order_ids: Vec::new(),
+            failure: None,
+        }
+    }
+
+    fn failure() -> OrderFailure {
+        OrderFailure {
+            stage: OrderStage::Post,
+            attempts: 1,
+            error: ExecutionError::NoLiquidity("no match".to_string()),
+        }
+    }
+
+    #[test]
+    fn failure_without_fills_fails_the_intent() {
+        let mut intent = intent();
+        intent.fail(failure());
+        assert_eq!(intent.state, OrderState::Failed);
+        assert!(intent.failure.is_some());
+    }
+
+    #[test]
+    fn failed_follow_up_keeps_earlier_fills() {
+        let mut intent = intent();
+        intent.spent = dec!(4);
+        intent.shares = dec!(8);
+        intent.transition(OrderState::PartiallyFilled);
+        intent.fail(failure());
+        assert_eq!(intent.state, OrderState::PartiallyFilled);
+        assert_eq!(intent.spent, dec!(4));
+        assert!(intent.failure.is_some());
+    }
+
+    /// Serves the CLOB on a local port. `answer` gets the method, the path and how many orders
+    /// were posted before, and returns the status and JSON body, or `None` to drop the
+    /// connection.
+    async fn mock_clob(answer: fn(&str, &str, usize) -> Option<(u16, String)>) -> String {
+        use std::sync::atomic::AtomicUsize;
+        use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let posts = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let posts = Arc::clone(&posts);
+                tokio::spawn(async move {
+                    let mut buffer = Vec::new();
+                    let head = loop {
+                        let mut chunk = [0u8; 4096];
+                        let read = socket.read(&mut chunk).await.unwrap_or(0);
+                        if read == 0 {
+                            return;
+                        }
+                        buffer.extend_from_slice(&chunk[..read]);
+                        let text = String::from_utf8_lossy(&buffer).to_string();
+                        let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                            continue;
+                        };
+                        let length = head
+                            .lines()
+                            .find_map(|line| {
+                                let (name, value) = line.split_once(':')?;
+                                name.eq_ignore_ascii_case("content-length")
+                                    .then(|| value.trim().parse::<usize>().ok())?
+                            })
+                            .unwrap_or(0);
+                        if body.len() >= length {
+                            break head.to_string();
+                        }
+                    };
+                    let mut request = head.split_whitespace();
+                    let (method, path) = (request.next().unwrap(), request.next().unwrap());
+                    let posted = if method == "POST" {
+                        posts.fetch_add(1, Ordering::SeqCst)
+                    } else {
+                        posts.load(Ordering::SeqCst)
+                    };
+                    let Some((status, body)) = answer(method, path, posted) else {
+                        return;
+                    };
+                    let reply = format!(
+                        "HTTP/1.1 {} OK\r\ncontent-type: application/json\r\n\
+                         content-length: {}\r\nconnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(reply.as_bytes()).await;
+                });
+            }
+        });
+        url
+    }
+
+    fn signer() -> PrivateKeySigner {
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+            .parse::<PrivateKeySigner>()
+            .unwrap()
+            .with_chain_id(Some(POLYGON))
+    }
+
+    fn credentials() -> serde_json::Value {
+        serde_json::json!({
+            "apiKey": "00000000-0000-0000-0000-000000000000",
+            "secret": "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=",
+            "passphrase": "passphrase",
+        })
+    }
+
+    /// A client for the CLOB at `url` with fixed credentials, authenticated without a request.
+    async fn authenticated(url: &str, signer: &PrivateKeySigner) -> ClobClient {
+        polymarket_client_sdk::clob::Client::new(url, Default::default())
+            .unwrap()
+            .authentication_builder(signer)
+            .credentials(serde_json::from_value(credentials()).unwrap())
+            .authenticate()
+            .await
+            .unwrap()
+    }
+
+    /// An EOA order manager that retries quickly and re-authenticates against `host`.
+    fn manager(client: SharedClient, signer: PrivateKeySigner, host: String) -> OrderManager {
+        let config = OrderConfig {
+            max_retries: 3,
+            retry_base: Duration::from_millis(1),
+            max_follow_ups: 0,
+            follow_up_delay: Duration::ZERO,
+            balance_pause: Duration::ZERO,
+            host,
+        };
+        OrderManager::new(
+            client,
+            Funder::new(signer, SignatureType::Eoa, None).unwrap(),
+            config,
+            Notifier::from_env(),
+            Arc::new(Store::open(std::path::Path::new(":memory:")).unwrap()),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn re_authentication_renews_the_account_client() {
+        // Every request on the old credentials is rejected; the new host issues fresh ones.
+        let stale =
+            mock_clob(|_, _, _| Some((401, r#"{"error":"Unauthorized"}"#.to_string()))).await;
+        let fresh = mock_clob(|method, path, _| match (method, path) {
+            (_, path) if path.starts_with("/auth/") => Some((200, credentials().to_string())),
+            ("GET", path) if path.starts_with("/balance-allowance") => {
+                Some((200, r#"{"balance":"25000000","allowances":{}}"#.to_string()))
+            }
+            ("GET", path) if path.starts_with("/data/orders") => Some((
+                200,
+                r#"{"data":[],"next_cursor":"LTE=","limit":100,"count":0}"#.to_string(),
+            )),
+            _ => Some((404, "null".to_string())),
+        })
+        .await;
+
+        let signer = signer();
+        let client = SharedClient::new(authenticated(&stale, &signer).await);
+        let account = AccountState::new(client.clone());
+        let manager = manager(client, signer, fresh);
+        assert!(account.refresh().await.is_err());
+
+        manager.reauthenticate(0).await.unwrap();
+        assert_eq!(manager.client().1, 1);
+        account.refresh().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn post_that_timed_out_then_was_rejected_as_duplicate_keeps_its_fill() {
+        // The first post reaches the exchange and fills but the response is lost; the retry of
+        // the same signed order is rejected as a duplicate.
+        let url = mock_clob(|method, path, posted| match (method, path) {
+            ("GET", path) if path.starts_with("/neg-risk") => {
+                Some((200, r#"{"neg_risk":false}"#.to_string()))
+            }
+            ("POST", "/orders") if posted == 0 => None,
+            ("POST", "/orders") => {
+                Some((400, r#"{"error":"order is invalid. Duplicated."}"#.into()))
+            }
+            ("GET", path) if path.starts_with("/data/order/0x") => Some((
+                200,
+                serde_json::json!({
+                    "id": &path["/data/order/".len()..],
+                    "status": "MATCHED",
+                    "owner": "00000000-0000-0000-0000-000000000000",
+                    "maker_address": "0x0000000000000000000000000000000000000001",
+                    "market": "0xmarket",
+                    "asset_id": "1",
+                    "side": "BUY",
+                    "original_size": "20",
+                    "size_matched": "8",
+                    "price": "0.5",
+                    "associate_trades": ["trade"],
+                    "outcome": "Yes",
+                    "created_at": 0,
+                    "expiration": "0",
+                    "order_type": "FAK",
+                })
+                .to_string(),
+            )),
+            _ => Some((404, "null".to_string())),
+        })
+        .await;
+
+        let signer = signer();
+        let client = authenticated(&url, &signer).await;
+
+        let mut order = SignableOrder::default();
+        order.order.tokenId = U256::from(1);
+        order.order.makerAmount = U256::from(10_000_000);
+        order.order.takerAmount = U256::from(20_000_000);
+        order.order_type = OrderType::FAK;
+        let signed = client.sign(&signer, order).await.unwrap();
+        let id = order_id(&client, Some(POLYGON), &signed.order)
+            .await
+            .unwrap();
+        let hash = id.parse::<alloy::primitives::B256>().unwrap();
+        assert_eq!(
+            signed
+                .signature
+                .recover_address_from_prehash(&hash)
+                .unwrap(),
+            signer.address()
+        );
+
+        let manager = manager(SharedClient::new(client), signer, url);
+        let responses = manager.post(&signed).await.unwrap();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].order_id, id);
+        assert_eq!(responses[0].taking_amount, dec!(8));
+        assert_eq!(responses[0].making_amount, dec!(4));
+        assert_eq!(manager.error_counts().get("other"), Some(&1));
+    }
+}
@@ -0,0 +1,129 @@
+use chrono::{DateTime, Utc};
+use polymarket_client_sdk::types::OrderSummary;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+/// Result of matching a simulated FAK buy against an order book snapshot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatedFill {
+    pub spent: Decimal,
+    pub shares: Decimal,
+}
+
+impl SimulatedFill {
+    pub fn avg_price(&self) -> Option<Decimal> {
+        (!self.shares.is_zero()).then(|| self.spent / self.shares)
+    }
+}
+
+/// Walks `asks` from the best price up, spending at most `amount` USDC at prices no worse than
+/// `limit_price`. Whatever cannot be filled is cancelled, as with a FAK order, so the fill may be
+/// partial or empty.
+pub fn simulate_buy(asks: &[OrderSummary], amount: Decimal, limit_price: Decimal) -> SimulatedFill {
+    let mut levels = asks
+        .iter()
+        .filter(|level| level.price <= limit_price && !level.price.is_zero())
+        .collect::<Vec<_>>();
+    levels.sort_by_key(|level| level.price);
+
+    let mut remaining = amount;
+    let mut shares = Decimal::ZERO;
+    for level in levels {
+        if remaining <= Decimal::ZERO {
+            break;
+        }
+        let level_cost = level.price * level.size;
+        let take_cost = remaining.min(level_cost);
+        shares += take_cost / level.price;
+        remaining -= take_cost;
+    }
+
+    SimulatedFill {
+        spent: amount - remaining,
+        shares,
+    }
+}
+
+/// One simulated copy, as written to the paper ledger.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperFill {
+    pub timestamp: DateTime<Utc>,
+    /// Target the fill copied; absent in ledgers written before attribution.
+    #[serde(default)]
+    pub target: Option<String>,
+    pub token_id: String,
+    pub condition_id: String,
+    pub requested: Decimal,
+    pub spent: Decimal,
+    pub shares: Decimal,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PaperPosition {
+    pub condition_id: String,
+    pub shares: Decimal,
+    pub cost: Decimal,
+}
+
+/// Append-only ledger of paper fills with the positions they add up to.
+pub struct PaperLedger {
+    path: PathBuf,
+    positions: HashMap<String, PaperPosition>,
+}
+
+impl PaperLedger {
+    /// Opens the ledger at `path`, replaying any fills already recorded there.
+    pub fn open(path: PathBuf) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let mut ledger = Self {
+            path,
+            positions: HashMap::new(),
+        };
+
+        if ledger.path.exists() {
+            let reader = BufReader::new(fs::File::open(&ledger.path)?);
+            for line in reader.lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let fill = serde_json::from_str::<PaperFill>(&line)?;
+                ledger.apply(&fill);
+            }
+        }
+
+        println!(
+            "Paper ledger {}: {} open positions",
+            ledger.path.display(),
+            ledger.positions.len()
+        );
+        Ok(ledger)
+    }
+
+    fn apply(&mut self, fill: &PaperFill) {
+        let position = self.positions.entry(fill.token_id.clone()).or_default();
+        position.condition_id = fill.condition_id.clone();
+        position.shares += fill.shares;
+        position.cost += fill.spent;
+    }
+
+    pub fn record(
+        &mut self,
+        fill: PaperFill,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&fill)?)?;
+        self.apply(&fill);
+        Ok(())
+    }
+
+    pub fn positions(&self) -> &HashMap<String, PaperPosition> {
+        &self.positions
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+use crate::filters::MarketFilter;
+use crate::gates::{GateConfig, SkipReason};
+use crate::market_catalog::MarketInfo;
+use crate::sizing::{normalize_buy_amount, SizingConfig};
+use crate::trade::TargetTrade;
+
+/// A copy order the pipeline has decided to place.
+#[derive(Debug, Clone)]
+pub struct CopyPlan {
+    pub token_id: String,
+    pub condition_id: String,
+    /// USDC to spend, already normalized to the market's tick and minimum size.
+    pub amount: Decimal,
+    /// Price the amount was sized against: the best ask when known, otherwise the target's fill.
+    pub reference_price: Decimal,
+}
+
+/// The copy decision: market filters, price drift and sizing. Network-free so the live bot,
+/// paper mode and backtests all make the same call.
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    pub gates: GateConfig,
+    pub filter: MarketFilter,
+    pub sizing: SizingConfig,
+}
+
+impl Pipeline {
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self {
+            gates: GateConfig::from_env()?,
+            filter: MarketFilter::from_env()?,
+            sizing: SizingConfig::from_env()?,
+        })
+    }
+
+    /// Whether [`Pipeline::plan`] needs the current best ask.
+    pub fn needs_book(&self) -> bool {
+        self.gates.max_price_drift.is_some()
+    }
+
+    pub fn plan(
+        &self,
+        trade: &TargetTrade,
+        market: &MarketInfo,
+        best_ask: Option<Decimal>,
+        now: DateTime<Utc>,
+    ) -> Result<CopyPlan, SkipReason> {
+        self.filter.check_trade(&market.condition_id, trade.price)?;
+        self.filter.check_market(market, now)?;
+        self.gates.check_drift(trade.price, best_ask)?;
+
+        let reference_price = best_ask.unwrap_or(trade.price);
+        let amount = normalize_buy_amount(
+            self.sizing.copy_amount(trade.price, trade.size),
+            reference_price,
+            market,
+            self.sizing.rounding,
+            self.sizing.max_amount,
+        )?;
+
+        Ok(CopyPlan {
+            token_id: trade.asset.clone(),
+            condition_id: market.condition_id.clone(),
+            amount,
+            reference_price,
+        })
+    }
+}
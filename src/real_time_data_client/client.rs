@@ -49,10 +49,8 @@ pub struct RealTimeDataClient {
 }
 
 enum ClientCommand {
-    Connect,
     Disconnect,
     Subscribe(SubscriptionMessage),
-    Unsubscribe(SubscriptionMessage),
 }
 
 impl RealTimeDataClient {
@@ -66,9 +64,9 @@ impl RealTimeDataClient {
             host,
             ping_interval,
             auto_reconnect,
-            on_connect: args.on_connect.map(|cb| Arc::from(cb)),
-            on_message: args.on_message.map(|cb| Arc::from(cb)),
-            on_status_change: args.on_status_change.map(|cb| Arc::from(cb)),
+            on_connect: args.on_connect.map(Arc::from),
+            on_message: args.on_message.map(Arc::from),
+            on_status_change: args.on_status_change.map(Arc::from),
             command_tx: None,
         }
     }
@@ -190,15 +188,6 @@ impl RealTimeDataClient {
                                             }
                                         }
                                     }
-                                    Some(ClientCommand::Unsubscribe(msg)) => {
-                                        let action = msg.to_unsubscribe_action();
-                                        if let Ok(json) = serde_json::to_string(&action) {
-                                            if let Err(e) = write.send(WsMessage::Text(json)).await {
-                                                eprintln!("Error sending unsubscribe: {}", e);
-                                            }
-                                        }
-                                    }
-                                    Some(ClientCommand::Connect) => {}
                                     None => {
                                         _connection_closed = true;
                                         break;
@@ -249,12 +238,6 @@ impl RealTimeDataClient {
         }
     }
 
-    pub fn unsubscribe(&self, msg: SubscriptionMessage) {
-        if let Some(tx) = &self.command_tx {
-            let _ = tx.send(ClientCommand::Unsubscribe(msg));
-        }
-    }
-
     fn notify_status_change(&self, status: ConnectionStatus) {
         if let Some(cb) = &self.on_status_change {
             cb(status);
@@ -77,11 +77,4 @@ impl SubscriptionMessage {
             subscriptions: self.subscriptions.clone(),
         }
     }
-
-    pub fn to_unsubscribe_action(&self) -> SubscriptionAction {
-        SubscriptionAction {
-            action: "unsubscribe".to_string(),
-            subscriptions: self.subscriptions.clone(),
-        }
-    }
 }
@@ -0,0 +1,363 @@
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::backtest::{DEFAULT_LOG_PATH, DEFAULT_MARKET_CACHE_PATH};
+use crate::config::env_parse;
+use crate::discover::{wallet_stats, ActivityLog, WalletStats};
+use crate::store::Store;
+use crate::targets::Targets;
+
+const DEFAULT_TOP_N: usize = 3;
+const DEFAULT_WINDOW_DAYS: i64 = 7;
+const DEFAULT_MIN_TRADES: usize = 10;
+const DEFAULT_HYSTERESIS: usize = 2;
+const DEFAULT_MAX_CHANGES_PER_DAY: usize = 2;
+const DEFAULT_INTERVAL_SECS: u64 = 60 * 60;
+
+/// Settings for automatic target rotation.
+#[derive(Debug, Clone)]
+pub struct AutoFollowConfig {
+    pub log_path: PathBuf,
+    pub cache_path: PathBuf,
+    /// Rolling window the wallets are scored over.
+    pub window: Duration,
+    /// Number of auto-followed targets, on top of the pinned ones.
+    pub top_n: usize,
+    pub min_trades: usize,
+    /// Extra ranks an active target may fall below the top N before it is demoted.
+    pub hysteresis: usize,
+    pub max_changes_per_day: usize,
+    pub interval: std::time::Duration,
+}
+
+impl AutoFollowConfig {
+    /// Reads `AUTO_FOLLOW` (`true` enables) and the `AUTO_FOLLOW_*` settings. Returns `None` when
+    /// auto-follow is off.
+    pub fn from_env() -> Result<Option<Self>, Box<dyn std::error::Error + Send + Sync>> {
+        if !env_parse("AUTO_FOLLOW", false)? {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            log_path: PathBuf::from(DEFAULT_LOG_PATH),
+            cache_path: PathBuf::from(
+                env::var("AUTO_FOLLOW_CACHE_PATH")
+                    .unwrap_or_else(|_| DEFAULT_MARKET_CACHE_PATH.to_string()),
+            ),
+            window: Duration::days(env_parse("AUTO_FOLLOW_WINDOW_DAYS", DEFAULT_WINDOW_DAYS)?),
+            top_n: env_parse("AUTO_FOLLOW_TOP_N", DEFAULT_TOP_N)?,
+            min_trades: env_parse("AUTO_FOLLOW_MIN_TRADES", DEFAULT_MIN_TRADES)?,
+            hysteresis: env_parse("AUTO_FOLLOW_HYSTERESIS", DEFAULT_HYSTERESIS)?,
+            max_changes_per_day: env_parse(
+                "AUTO_FOLLOW_MAX_CHANGES_PER_DAY",
+                DEFAULT_MAX_CHANGES_PER_DAY,
+            )?,
+            interval: std::time::Duration::from_secs(env_parse(
+                "AUTO_FOLLOW_INTERVAL_SECS",
+                DEFAULT_INTERVAL_SECS,
+            )?),
+        }))
+    }
+}
+
+fn describe(rank: usize, stats: &WalletStats) -> String {
+    format!(
+        "rank {}, realized pnl {} USDC over {} resolved markets, {} trades in {} markets, volume {} USDC, avg entry {}",
+        rank + 1,
+        stats.realized_pnl.round_dp(2),
+        stats.resolved_markets,
+        stats.trades,
+        stats.markets,
+        stats.volume.round_dp(2),
+        stats
+            .avg_entry_price
+            .map_or_else(|| "-".to_string(), |p| p.round_dp(3).to_string())
+    )
+}
+
+/// Periodically re-scores wallets on their recent realized PnL and rotates the auto-followed
+/// targets. Promotions and demotions are kept in the [`Store`], so the promoted targets and the
+/// daily change budget survive restarts.
+pub struct AutoFollow {
+    config: AutoFollowConfig,
+    targets: Targets,
+    store: Arc<Store>,
+    /// Trades of the rolling window, topped up from the log on each rotation.
+    log: ActivityLog,
+    changes: VecDeque<DateTime<Utc>>,
+}
+
+impl AutoFollow {
+    /// Restores the targets promoted before and the changes made in the last 24 hours from
+    /// `store`.
+    pub fn new(
+        config: AutoFollowConfig,
+        targets: Targets,
+        store: Arc<Store>,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let promoted = store.rotated_targets()?;
+        for wallet in &promoted {
+            targets.promote(wallet);
+        }
+        let since = (Utc::now() - Duration::days(1)).timestamp();
+        let changes = store
+            .rotation_changes(since)?
+            .into_iter()
+            .filter_map(|at| DateTime::from_timestamp(at, 0))
+            .collect::<VecDeque<_>>();
+        if !promoted.is_empty() || !changes.is_empty() {
+            println!(
+                "Auto-follow: restored {} promoted targets and {} changes in the last day",
+                promoted.len(),
+                changes.len()
+            );
+        }
+        Ok(Self {
+            log: ActivityLog::new(config.log_path.clone()),
+            config,
+            targets,
+            store,
+            changes,
+        })
+    }
+
+    pub async fn run(mut self) {
+        let mut interval = tokio::time::interval(self.config.interval);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.rotate(Utc::now()).await {
+                eprintln!("Error rotating auto-follow targets: {}", e);
+            }
+        }
+    }
+
+    /// Whether another promotion or demotion fits in the last 24 hours' budget; records it if so.
+    fn take_change(&mut self, now: DateTime<Utc>) -> bool {
+        while self
+            .changes
+            .front()
+            .is_some_and(|at| now - *at >= Duration::days(1))
+        {
+            self.changes.pop_front();
+        }
+        if self.changes.len() >= self.config.max_changes_per_day {
+            return false;
+        }
+        self.changes.push_back(now);
+        true
+    }
+
+    /// Applies a promotion or demotion of `wallet` and persists it.
+    fn change(&self, wallet: &str, promoted: bool, now: DateTime<Utc>) {
+        if promoted {
+            self.targets.promote(wallet);
+        } else {
+            self.targets.demote(wallet);
+        }
+        if let Err(e) = self
+            .store
+            .record_rotation(wallet, promoted, now.timestamp())
+        {
+            eprintln!("Error persisting auto-follow change for {}: {}", wallet, e);
+        }
+    }
+
+    /// Re-scores wallets over the window ending at `now` and rotates the targets.
+    async fn rotate(
+        &mut self,
+        now: DateTime<Utc>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let ranking = wallet_stats(
+            &mut self.log,
+            Some(now - self.config.window),
+            self.config.min_trades,
+            Some(&self.config.cache_path),
+        )
+        .await?;
+        self.apply_ranking(ranking, now);
+        Ok(())
+    }
+
+    /// Demotes targets that fell out of the top `top_n + hysteresis` or stopped making money,
+    /// then promotes the best profitable wallets into free slots.
+    fn apply_ranking(&mut self, mut ranking: Vec<WalletStats>, now: DateTime<Utc>) {
+        ranking.retain(|stats| !self.targets.is_pinned(&stats.wallet, &stats.name));
+        ranking.sort_by(|a, b| {
+            b.realized_pnl
+                .cmp(&a.realized_pnl)
+                .then(b.trades.cmp(&a.trades))
+                .then(a.wallet.cmp(&b.wallet))
+        });
+        let rank_of = |wallet: &str| ranking.iter().position(|s| s.wallet == wallet);
+
+        for wallet in self.targets.rotated() {
+            let reason = match rank_of(&wallet) {
+                None => format!(
+                    "fewer than {} trades in the last {} days",
+                    self.config.min_trades,
+                    self.config.window.num_days()
+                ),
+                Some(rank) if rank >= self.config.top_n + self.config.hysteresis => {
+                    describe(rank, &ranking[rank])
+                }
+                Some(rank) if ranking[rank].realized_pnl <= Decimal::ZERO => {
+                    describe(rank, &ranking[rank])
+                }
+                Some(_) => continue,
+            };
+            if !self.take_change(now) {
+                println!(
+                    "Auto-follow: demotion of {} deferred, daily change limit reached ({})",
+                    wallet, reason
+                );
+                continue;
+            }
+            self.change(&wallet, false, now);
+            println!("Auto-follow: demoted {} ({})", wallet, reason);
+        }
+
+        for (rank, stats) in ranking.iter().enumerate().take(self.config.top_n) {
+            if self.targets.rotated().len() >= self.config.top_n
+                || stats.realized_pnl <= Decimal::ZERO
+            {
+                break;
+            }
+            if self.targets.rotated().contains(&stats.wallet) {
+                continue;
+            }
+            if !self.take_change(now) {
+                println!(
+                    "Auto-follow: promotion of {} deferred, daily change limit reached ({})",
+                    stats.wallet,
+                    describe(rank, stats)
+                );
+                break;
+            }
+            self.change(&stats.wallet, true, now);
+            println!(
+                "Auto-follow: promoted {} ({})",
+                stats.wallet,
+                describe(rank, stats)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn auto_follow(
+        max_changes_per_day: usize,
+        targets: &Targets,
+        store: &Arc<Store>,
+    ) -> AutoFollow {
+        AutoFollow::new(
+            config(max_changes_per_day),
+            targets.clone(),
+            Arc::clone(store),
+        )
+        .unwrap()
+    }
+
+    fn memory_store() -> Arc<Store> {
+        Arc::new(Store::open(Path::new(":memory:")).unwrap())
+    }
+
+    fn config(max_changes_per_day: usize) -> AutoFollowConfig {
+        AutoFollowConfig {
+            log_path: PathBuf::from(DEFAULT_LOG_PATH),
+            cache_path: PathBuf::from(DEFAULT_MARKET_CACHE_PATH),
+            window: Duration::days(DEFAULT_WINDOW_DAYS),
+            top_n: 2,
+            min_trades: 1,
+            hysteresis: 1,
+            max_changes_per_day,
+            interval: std::time::Duration::from_secs(DEFAULT_INTERVAL_SECS),
+        }
+    }
+
+    fn ranking(pnls: &[(&str, i64)]) -> Vec<WalletStats> {
+        pnls.iter()
+            .map(|(wallet, pnl)| WalletStats {
+                wallet: wallet.to_string(),
+                name: String::new(),
+                trades: 10,
+                buys: 10,
+                sells: 0,
+                volume: Decimal::from(100),
+                avg_entry_price: None,
+                markets: 1,
+                resolved_markets: 1,
+                realized_pnl: Decimal::from(*pnl),
+                first_trade: DateTime::UNIX_EPOCH,
+                last_trade: DateTime::UNIX_EPOCH,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn targets_are_kept_within_the_hysteresis_band() {
+        let targets = Targets::default();
+        let mut rotation = auto_follow(10, &targets, &memory_store());
+        let now = Utc::now();
+
+        rotation.apply_ranking(ranking(&[("a", 30), ("b", 20), ("c", 10)]), now);
+        assert_eq!(targets.rotated(), ["a", "b"]);
+
+        // b drops to third, still within top 2 + hysteresis 1.
+        rotation.apply_ranking(ranking(&[("c", 40), ("a", 30), ("b", 20)]), now);
+        assert_eq!(targets.rotated(), ["a", "b"]);
+
+        // b drops to fourth and makes room for c.
+        rotation.apply_ranking(ranking(&[("c", 40), ("d", 35), ("a", 30), ("b", 20)]), now);
+        assert_eq!(targets.rotated(), ["a", "c"]);
+
+        // a stops making money, and no other wallet is profitable.
+        rotation.apply_ranking(ranking(&[("c", 40), ("a", 0), ("d", -5)]), now);
+        assert_eq!(targets.rotated(), ["c"]);
+    }
+
+    #[test]
+    fn changes_are_limited_per_day() {
+        let targets = Targets::default();
+        let mut rotation = auto_follow(1, &targets, &memory_store());
+        let now = Utc::now();
+
+        rotation.apply_ranking(ranking(&[("a", 30), ("b", 20)]), now);
+        assert_eq!(targets.rotated(), ["a"]);
+        rotation.apply_ranking(ranking(&[("a", 30), ("b", 20)]), now + Duration::hours(23));
+        assert_eq!(targets.rotated(), ["a"]);
+        rotation.apply_ranking(ranking(&[("a", 30), ("b", 20)]), now + Duration::days(1));
+        assert_eq!(targets.rotated(), ["a", "b"]);
+    }
+
+    #[test]
+    fn promotions_and_the_change_budget_survive_restarts() {
+        let store = memory_store();
+        let now = Utc::now();
+        auto_follow(1, &Targets::default(), &store)
+            .apply_ranking(ranking(&[("a", 30), ("b", 20)]), now);
+
+        let targets = Targets::default();
+        let mut restarted = auto_follow(1, &targets, &store);
+        assert_eq!(targets.rotated(), ["a"]);
+        restarted.apply_ranking(ranking(&[("a", 30), ("b", 20)]), now);
+        assert_eq!(targets.rotated(), ["a"]);
+    }
+
+    #[test]
+    fn pinned_names_are_not_rotated() {
+        let targets = Targets::new(vec!["trader".to_string()]);
+        let mut rotation = auto_follow(10, &targets, &memory_store());
+        let mut ranking = ranking(&[("0xpinned", 30), ("b", 20)]);
+        ranking[0].name = "Trader".to_string();
+
+        rotation.apply_ranking(ranking, Utc::now());
+        assert_eq!(targets.rotated(), ["b"]);
+    }
+}
@@ -0,0 +1,611 @@
+use alloy::primitives::{hex, keccak256, B256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::client::RpcClient;
+use alloy::rpc::json_rpc::{
+    Id, Request, RequestPacket, Response, ResponsePacket, ResponsePayload, SerializedRequest,
+};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::transports::http::Http;
+use alloy::transports::{TransportError, TransportErrorKind, TransportFut};
+use futures_util::future::join_all;
+use serde_json::value::RawValue;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::Service;
+
+use crate::config::env_parse;
+
+pub const DEFAULT_RPC_URL: &str = "https://polygon-rpc.com";
+
+/// Requests remembered per endpoint for its error rate.
+const OUTCOME_WINDOW: usize = 20;
+/// Endpoints failing more often than this are ranked behind healthy ones.
+const MAX_ERROR_RATE: f64 = 0.5;
+/// Weight of the newest sample in the moving latency average.
+const LATENCY_SMOOTHING: f64 = 0.3;
+
+/// Reads whose answer is compared across endpoints when a quorum is configured, unless they ask
+/// for the `pending` block.
+const QUORUM_METHODS: &[&str] = &[
+    "eth_call",
+    "eth_getBalance",
+    "eth_getCode",
+    "eth_getStorageAt",
+    "eth_getTransactionCount",
+];
+
+/// Errors a node returns for a raw transaction that it, or an endpoint tried before it, already
+/// accepted.
+const ALREADY_SENT_ERRORS: &[&str] = &[
+    "already known",
+    "known transaction",
+    "already imported",
+    "nonce too low",
+];
+
+/// Whether `request` is a [`QUORUM_METHODS`] read at a block every node agrees on: `latest`,
+/// `safe`, `finalized`, a number or a hash. With `pending`, each node answers from its own
+/// mempool, so honest endpoints disagree.
+fn is_quorum_read(request: &SerializedRequest) -> bool {
+    if !QUORUM_METHODS.contains(&request.method()) {
+        return false;
+    }
+    let params = request
+        .params()
+        .and_then(|params| serde_json::from_str::<Vec<serde_json::Value>>(params.get()).ok())
+        .unwrap_or_default();
+    // The block is the last parameter, and `latest` when omitted.
+    let positional = if request.method() == "eth_getStorageAt" {
+        3
+    } else {
+        2
+    };
+    params.len() < positional || params.last().and_then(|tag| tag.as_str()) != Some("pending")
+}
+
+/// The hash of the transaction in an `eth_sendRawTransaction` request.
+fn raw_transaction_hash(request: &SerializedRequest) -> Option<B256> {
+    if request.method() != "eth_sendRawTransaction" {
+        return None;
+    }
+    let params = serde_json::from_str::<Vec<String>>(request.params()?.get()).ok()?;
+    let raw = hex::decode(params.first()?).ok()?;
+    Some(keccak256(raw))
+}
+
+/// A successful `eth_sendRawTransaction` response carrying `hash`, for a transaction an earlier
+/// attempt already delivered.
+fn sent_response(id: Id, hash: B256) -> Result<ResponsePacket, TransportError> {
+    let payload = RawValue::from_string(format!("\"{}\"", hash))
+        .map_err(|e| TransportErrorKind::custom_str(&e.to_string()))?;
+    Ok(ResponsePacket::Single(Response {
+        id,
+        payload: ResponsePayload::Success(payload),
+    }))
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcConfig {
+    pub urls: Vec<String>,
+    /// Blocks an endpoint may trail the highest one seen before it counts as unhealthy.
+    pub max_block_lag: u64,
+    pub health_interval: Duration,
+    /// Endpoints that must return the same answer for a [`QUORUM_METHODS`] read; 1 disables.
+    pub read_quorum: usize,
+}
+
+impl RpcConfig {
+    /// Reads `RPC_URLS` (comma-separated, default the public Polygon RPC), `RPC_MAX_BLOCK_LAG`
+    /// (default 5), `RPC_HEALTH_CHECK_SECS` (default 30) and `RPC_READ_QUORUM` (default 1).
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let urls = env::var("RPC_URLS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .collect::<Vec<_>>();
+        let urls = if urls.is_empty() {
+            vec![DEFAULT_RPC_URL.to_string()]
+        } else {
+            urls
+        };
+        let read_quorum = env_parse("RPC_READ_QUORUM", 1usize)?.max(1);
+        if read_quorum > urls.len() {
+            return Err(format!(
+                "RPC_READ_QUORUM is {} but only {} RPC endpoints are configured",
+                read_quorum,
+                urls.len()
+            )
+            .into());
+        }
+        Ok(Self {
+            urls,
+            max_block_lag: env_parse("RPC_MAX_BLOCK_LAG", 5)?,
+            health_interval: Duration::from_secs(env_parse("RPC_HEALTH_CHECK_SECS", 30)?.max(1)),
+            read_quorum,
+        })
+    }
+}
+
+/// What an endpoint's recent requests and health checks say about it.
+#[derive(Debug, Default)]
+struct Health {
+    /// Outcomes of the most recent requests, `true` for success.
+    outcomes: VecDeque<bool>,
+    latency_ms: Option<f64>,
+    block: u64,
+}
+
+impl Health {
+    fn record(&mut self, ok: bool, elapsed: Duration) {
+        if self.outcomes.len() == OUTCOME_WINDOW {
+            self.outcomes.pop_front();
+        }
+        self.outcomes.push_back(ok);
+        if ok {
+            let ms = elapsed.as_secs_f64() * 1000.0;
+            self.latency_ms = Some(match self.latency_ms {
+                Some(avg) => avg + (ms - avg) * LATENCY_SMOOTHING,
+                None => ms,
+            });
+        }
+    }
+
+    fn error_rate(&self) -> f64 {
+        if self.outcomes.is_empty() {
+            return 0.0;
+        }
+        self.outcomes.iter().filter(|ok| !**ok).count() as f64 / self.outcomes.len() as f64
+    }
+}
+
+struct Endpoint {
+    url: String,
+    transport: Http<alloy::transports::http::reqwest::Client>,
+    health: Mutex<Health>,
+}
+
+struct Inner {
+    endpoints: Vec<Endpoint>,
+    config: RpcConfig,
+}
+
+/// A JSON-RPC transport over several endpoints. Requests go to the healthiest endpoint and fail
+/// over to the next on transport errors and rate limits. Endpoints are ranked by block lag,
+/// error rate and latency, which a background task refreshes with `eth_blockNumber`.
+#[derive(Clone)]
+pub struct RpcPool {
+    inner: Arc<Inner>,
+}
+
+impl RpcPool {
+    /// Builds the pool, checks every endpoint once and starts the periodic health checks, which
+    /// stop when the last clone of the pool is dropped.
+    pub async fn connect(
+        config: RpcConfig,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let endpoints = config
+            .urls
+            .iter()
+            .map(|url| {
+                Ok(Endpoint {
+                    url: url.clone(),
+                    transport: Http::new(url.parse()?),
+                    health: Mutex::new(Health::default()),
+                })
+            })
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error + Send + Sync>>>()?;
+        let pool = Self {
+            inner: Arc::new(Inner { endpoints, config }),
+        };
+        pool.check_health().await;
+        tokio::spawn(run_health_checks(
+            Arc::downgrade(&pool.inner),
+            pool.inner.config.health_interval,
+        ));
+        Ok(pool)
+    }
+
+    /// Polls every endpoint's block height, recording latency and failures.
+    async fn check_health(&self) {
+        let checks = self.inner.endpoints.iter().map(|endpoint| async move {
+            let packet = match Request::new("eth_blockNumber", Id::Number(0), ()).serialize() {
+                Ok(request) => RequestPacket::Single(request),
+                Err(_) => return,
+            };
+            let started = Instant::now();
+            let result = endpoint.transport.clone().call(packet).await;
+            let block = result
+                .ok()
+                .and_then(|response| match response.single_payload() {
+                    Some(ResponsePayload::Success(raw)) => {
+                        let hex = serde_json::from_str::<String>(raw.get()).ok()?;
+                        u64::from_str_radix(hex.trim_start_matches("0x"), 16).ok()
+                    }
+                    _ => None,
+                });
+            let mut health = endpoint.health.lock().unwrap();
+            health.record(block.is_some(), started.elapsed());
+            if let Some(block) = block {
+                health.block = block;
+            }
+        });
+        join_all(checks).await;
+    }
+
+    /// Endpoint indices, best first: healthy before lagging or failing, then by error rate and
+    /// latency.
+    fn ranked(&self) -> Vec<usize> {
+        let endpoints = &self.inner.endpoints;
+        let snapshot = endpoints
+            .iter()
+            .map(|endpoint| {
+                let health = endpoint.health.lock().unwrap();
+                (health.block, health.error_rate(), health.latency_ms)
+            })
+            .collect::<Vec<_>>();
+        let head = snapshot
+            .iter()
+            .map(|(block, _, _)| *block)
+            .max()
+            .unwrap_or(0);
+        let mut order = (0..endpoints.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| {
+            let key = |i: usize| {
+                let (block, error_rate, latency_ms) = snapshot[i];
+                let healthy = head.saturating_sub(block) <= self.inner.config.max_block_lag
+                    && error_rate < MAX_ERROR_RATE;
+                (
+                    !healthy,
+                    error_rate + latency_ms.unwrap_or(f64::MAX) / 10_000.0,
+                )
+            };
+            let (a, b) = (key(*a), key(*b));
+            a.0.cmp(&b.0).then(a.1.total_cmp(&b.1))
+        });
+        order
+    }
+
+    /// One line per endpoint, best first, for logs.
+    pub fn report(&self) -> String {
+        self.ranked()
+            .into_iter()
+            .map(|i| {
+                let endpoint = &self.inner.endpoints[i];
+                let health = endpoint.health.lock().unwrap();
+                format!(
+                    "{}: block {}, {:.0}% errors, {}",
+                    endpoint.url,
+                    health.block,
+                    health.error_rate() * 100.0,
+                    health
+                        .latency_ms
+                        .map(|ms| format!("{:.0}ms", ms))
+                        .unwrap_or_else(|| "no latency yet".to_string())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    /// Sends `packet` to `endpoint` and records the outcome. Rate limits and other retryable
+    /// JSON-RPC errors count as failures, so the caller can fail over.
+    async fn send_to(
+        &self,
+        index: usize,
+        packet: RequestPacket,
+    ) -> Result<ResponsePacket, TransportError> {
+        let endpoint = &self.inner.endpoints[index];
+        let started = Instant::now();
+        let result = endpoint.transport.clone().call(packet).await;
+        let result = match result {
+            Ok(response) if response.as_error().is_some_and(|e| e.is_retry_err()) => {
+                Err(TransportErrorKind::custom_str(&format!(
+                    "{} asked to retry: {}",
+                    endpoint.url,
+                    response.first_error_message().unwrap_or_default()
+                )))
+            }
+            result => result,
+        };
+        endpoint
+            .health
+            .lock()
+            .unwrap()
+            .record(result.is_ok(), started.elapsed());
+        result
+    }
+
+    /// Sends `packet` to the best endpoint, failing over to the next on errors. A raw transaction
+    /// may have reached a node whose answer was lost, so after a failover, a node that reports
+    /// it as already known or its nonce as used counts as a successful send of it.
+    async fn request(self, packet: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let request = packet.as_single();
+        if request.is_some_and(is_quorum_read) && self.inner.config.read_quorum > 1 {
+            return self.quorum_request(packet).await;
+        }
+        let raw_tx = request
+            .and_then(|request| Some((request.id().clone(), raw_transaction_hash(request)?)));
+        let mut last_error = None;
+        for index in self.ranked() {
+            match self.send_to(index, packet.clone()).await {
+                Ok(response) => {
+                    let already_sent = response.first_error_message().is_some_and(|message| {
+                        let message = message.to_lowercase();
+                        ALREADY_SENT_ERRORS
+                            .iter()
+                            .any(|error| message.contains(error))
+                    });
+                    match &raw_tx {
+                        Some((id, hash)) if already_sent && last_error.is_some() => {
+                            println!(
+                                "RPC {} already has tx {} sent before failing over",
+                                self.inner.endpoints[index].url, hash
+                            );
+                            return sent_response(id.clone(), *hash);
+                        }
+                        _ => return Ok(response),
+                    }
+                }
+                Err(e) => {
+                    eprintln!(
+                        "RPC {} failed, failing over: {}",
+                        self.inner.endpoints[index].url, e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error
+            .unwrap_or_else(|| TransportErrorKind::custom_str("no RPC endpoints configured")))
+    }
+
+    /// Sends `packet` to every endpoint and returns an answer that at least `read_quorum` of them
+    /// agree on.
+    async fn quorum_request(
+        &self,
+        packet: RequestPacket,
+    ) -> Result<ResponsePacket, TransportError> {
+        let order = self.ranked();
+        let responses = join_all(
+            order
+                .iter()
+                .map(|index| self.send_to(*index, packet.clone())),
+        )
+        .await;
+        let mut votes = HashMap::<String, (usize, ResponsePacket)>::new();
+        for response in responses.into_iter().flatten() {
+            let key = match response.single_payload() {
+                Some(ResponsePayload::Success(raw)) => raw.get().to_string(),
+                Some(ResponsePayload::Failure(error)) => {
+                    format!("error {} {}", error.code, error.message)
+                }
+                None => continue,
+            };
+            let entry = votes.entry(key).or_insert((0, response));
+            entry.0 += 1;
+            if entry.0 >= self.inner.config.read_quorum {
+                return Ok(entry.1.clone());
+            }
+        }
+        Err(TransportErrorKind::custom_str(&format!(
+            "no {} RPC endpoints agreed on the answer",
+            self.inner.config.read_quorum
+        )))
+    }
+}
+
+impl Service<RequestPacket> for RpcPool {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, packet: RequestPacket) -> Self::Future {
+        Box::pin(self.clone().request(packet))
+    }
+}
+
+async fn run_health_checks(inner: Weak<Inner>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        RpcPool { inner }.check_health().await;
+    }
+}
+
+/// A provider that signs with `signer` and sends through an [`RpcPool`] built from the
+/// environment. Approvals, balance checks, redemptions and the on-chain commands all use it.
+pub async fn connect(
+    signer: PrivateKeySigner,
+) -> Result<impl Provider + Clone, Box<dyn std::error::Error + Send + Sync>> {
+    let pool = RpcPool::connect(RpcConfig::from_env()?).await?;
+    println!("RPC endpoints: {}", pool.report());
+    Ok(ProviderBuilder::new()
+        .wallet(signer)
+        .connect_client(RpcClient::new(pool, false)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &'static str, params: serde_json::Value) -> SerializedRequest {
+        Request::new(method, Id::Number(1), params)
+            .serialize()
+            .unwrap()
+    }
+
+    #[test]
+    fn quorum_skips_pending_reads() {
+        let owner = "0x0000000000000000000000000000000000000001";
+        let count = |tag: &str| request("eth_getTransactionCount", serde_json::json!([owner, tag]));
+        assert!(is_quorum_read(&count("latest")));
+        assert!(is_quorum_read(&count("0x10")));
+        assert!(!is_quorum_read(&count("pending")));
+        assert!(is_quorum_read(&request(
+            "eth_getBalance",
+            serde_json::json!([owner])
+        )));
+        assert!(!is_quorum_read(&request(
+            "eth_getStorageAt",
+            serde_json::json!([owner, "0x0", "pending"])
+        )));
+        assert!(!is_quorum_read(&request(
+            "eth_blockNumber",
+            serde_json::json!([])
+        )));
+    }
+
+    #[test]
+    fn hashes_raw_transactions() {
+        let send = request("eth_sendRawTransaction", serde_json::json!(["0x0102"]));
+        assert_eq!(raw_transaction_hash(&send), Some(keccak256([1u8, 2])));
+        let call = request("eth_call", serde_json::json!(["0x0102"]));
+        assert_eq!(raw_transaction_hash(&call), None);
+    }
+
+    #[test]
+    fn already_sent_becomes_the_hash() {
+        let hash = keccak256([1u8]);
+        let response = sent_response(Id::Number(7), hash).unwrap();
+        let Some(ResponsePayload::Success(raw)) = response.single_payload() else {
+            panic!("expected a success");
+        };
+        assert_eq!(serde_json::from_str::<B256>(raw.get()).unwrap(), hash);
+    }
+
+    /// Serves JSON-RPC over HTTP on a local port: `eth_blockNumber` returns `block`, and every
+    /// other method gets `answer`, or a dropped connection when it is `None`.
+    async fn mock_endpoint(block: u64, answer: Option<serde_json::Value>) -> String {
+        use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let answer = answer.clone();
+                tokio::spawn(async move {
+                    let mut buffer = Vec::new();
+                    let body = loop {
+                        let mut chunk = [0u8; 4096];
+                        let read = socket.read(&mut chunk).await.unwrap_or(0);
+                        if read == 0 {
+                            return;
+                        }
+                        buffer.extend_from_slice(&chunk[..read]);
+                        let text = String::from_utf8_lossy(&buffer).to_string();
+                        let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                            continue;
+                        };
+                        let length = head
+                            .lines()
+                            .find_map(|line| {
+                                let (name, value) = line.split_once(':')?;
+                                name.eq_ignore_ascii_case("content-length")
+                                    .then(|| value.trim().parse::<usize>().ok())?
+                            })
+                            .unwrap_or(0);
+                        if body.len() >= length {
+                            break body.to_string();
+                        }
+                    };
+                    let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+                    let mut response = match (request["method"].as_str(), &answer) {
+                        (Some("eth_blockNumber"), _) => {
+                            serde_json::json!({ "result": format!("{:#x}", block) })
+                        }
+                        (_, Some(answer)) => answer.clone(),
+                        (_, None) => return,
+                    };
+                    response["jsonrpc"] = "2.0".into();
+                    response["id"] = request["id"].clone();
+                    let body = response.to_string();
+                    let reply = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                         content-length: {}\r\nconnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(reply.as_bytes()).await;
+                });
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn failed_over_send_already_known_is_sent() {
+        // The healthy endpoint drops the send; the lagging one, tried next, already has the tx.
+        let dropping = mock_endpoint(100, None).await;
+        let known = mock_endpoint(
+            1,
+            Some(serde_json::json!({ "error": { "code": -32000, "message": "already known" } })),
+        )
+        .await;
+        let pool = RpcPool::connect(RpcConfig {
+            urls: vec![dropping, known],
+            max_block_lag: 5,
+            health_interval: Duration::from_secs(3600),
+            read_quorum: 1,
+        })
+        .await
+        .unwrap();
+        let send = request("eth_sendRawTransaction", serde_json::json!(["0x0102"]));
+        let response = pool
+            .clone()
+            .request(RequestPacket::Single(send.clone()))
+            .await
+            .unwrap();
+        let Some(ResponsePayload::Success(raw)) = response.single_payload() else {
+            panic!("expected the hash, got {:?}", response);
+        };
+        assert_eq!(
+            serde_json::from_str::<B256>(raw.get()).unwrap(),
+            keccak256([1u8, 2])
+        );
+
+        // Without a failover first, the same error is the node's answer.
+        let known_only = mock_endpoint(
+            1,
+            Some(serde_json::json!({ "error": { "code": -32000, "message": "nonce too low" } })),
+        )
+        .await;
+        let pool = RpcPool::connect(RpcConfig {
+            urls: vec![known_only],
+            max_block_lag: 5,
+            health_interval: Duration::from_secs(3600),
+            read_quorum: 1,
+        })
+        .await
+        .unwrap();
+        let response = pool.request(RequestPacket::Single(send)).await.unwrap();
+        assert_eq!(response.first_error_message(), Some("nonce too low"));
+    }
+
+    #[test]
+    fn error_rate_covers_the_recent_window() {
+        let mut health = Health::default();
+        for _ in 0..OUTCOME_WINDOW {
+            health.record(false, Duration::from_millis(10));
+        }
+        assert_eq!(health.error_rate(), 1.0);
+        for _ in 0..OUTCOME_WINDOW / 2 {
+            health.record(true, Duration::from_millis(10));
+        }
+        assert_eq!(health.error_rate(), 0.5);
+        assert!(health.latency_ms.is_some());
+    }
+}
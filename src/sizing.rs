@@ -0,0 +1,175 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+use rust_decimal_macros::dec;
+use std::env;
+
+use crate::config::env_parse;
+use crate::gates::SkipReason;
+use crate::market_catalog::MarketInfo;
+
+/// Market buys are accepted with at most cent precision on the USDC amount.
+const USDC_AMOUNT_DECIMALS: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Up,
+    Down,
+}
+
+impl Rounding {
+    /// Reads `ORDER_ROUNDING` (`up` or `down`, default `down`).
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        match env::var("ORDER_ROUNDING")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "down" => Ok(Rounding::Down),
+            "up" => Ok(Rounding::Up),
+            other => Err(format!("ORDER_ROUNDING must be 'up' or 'down', got '{}'", other).into()),
+        }
+    }
+
+    fn strategy(self) -> RoundingStrategy {
+        match self {
+            Rounding::Up => RoundingStrategy::AwayFromZero,
+            Rounding::Down => RoundingStrategy::ToZero,
+        }
+    }
+}
+
+/// How a target trade is scaled into a copy order.
+#[derive(Debug, Clone)]
+pub struct SizingConfig {
+    pub multiplier: Decimal,
+    pub min_amount: Decimal,
+    pub max_amount: Decimal,
+    pub rounding: Rounding,
+}
+
+impl SizingConfig {
+    /// Reads `MULTIPLIER` (default 1), `MIN_ORDER_USDC` (default 1), `MAX_ORDER_USDC` (default 4)
+    /// and `ORDER_ROUNDING`.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let config = Self {
+            multiplier: env_parse("MULTIPLIER", Decimal::ONE)?,
+            min_amount: env_parse("MIN_ORDER_USDC", dec!(1))?,
+            max_amount: env_parse("MAX_ORDER_USDC", dec!(4))?,
+            rounding: Rounding::from_env()?,
+        };
+        if config.multiplier <= Decimal::ZERO {
+            return Err(format!("MULTIPLIER must be positive, got {}", config.multiplier).into());
+        }
+        if config.min_amount > config.max_amount {
+            return Err(format!(
+                "MIN_ORDER_USDC {} is greater than MAX_ORDER_USDC {}",
+                config.min_amount, config.max_amount
+            )
+            .into());
+        }
+        Ok(config)
+    }
+
+    /// `price × size × multiplier`, clamped to `[min_amount, max_amount]`.
+    pub fn copy_amount(&self, price: Decimal, size: Decimal) -> Decimal {
+        (price * size * self.multiplier).clamp(self.min_amount, self.max_amount)
+    }
+}
+
+/// Rounds `price` onto the market's tick grid. Buys round up since they pay the ask, capped at the
+/// highest valid price of `1 - tick_size`.
+pub fn price_to_tick(price: Decimal, tick_size: Decimal) -> Decimal {
+    if tick_size.is_zero() {
+        return price;
+    }
+    ((price / tick_size).ceil() * tick_size)
+        .min(Decimal::ONE - tick_size)
+        .normalize()
+}
+
+/// Smallest USDC amount the CLOB accepts for a buy in `market` at `price`: the minimum order size
+/// at the tick-aligned price, rounded up to cents.
+pub fn min_buy_amount(price: Decimal, market: &MarketInfo) -> Decimal {
+    (market.min_order_size * price_to_tick(price, market.tick_size))
+        .round_dp_with_strategy(USDC_AMOUNT_DECIMALS, RoundingStrategy::AwayFromZero)
+        .max(Decimal::new(1, USDC_AMOUNT_DECIMALS))
+}
+
+/// Normalizes a USDC buy amount to what the CLOB accepts for `market`.
+///
+/// The amount is rounded to cents with `rounding`. If the resulting share count at `price` is
+/// below the market's minimum order size, `Rounding::Up` raises it to the minimum as long as that
+/// stays within `max_amount`; otherwise the copy is skipped.
+pub fn normalize_buy_amount(
+    amount: Decimal,
+    price: Decimal,
+    market: &MarketInfo,
+    rounding: Rounding,
+    max_amount: Decimal,
+) -> Result<Decimal, SkipReason> {
+    let amount = amount.round_dp_with_strategy(USDC_AMOUNT_DECIMALS, rounding.strategy());
+    let min_amount = min_buy_amount(price, market);
+
+    if amount >= min_amount {
+        return Ok(amount);
+    }
+    if rounding == Rounding::Up && min_amount <= max_amount {
+        return Ok(min_amount);
+    }
+    Err(SkipReason::BelowMinimumSize { amount, min_amount })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market_catalog::tests::market;
+
+    #[test]
+    fn price_to_tick_rounds_up_and_caps() {
+        assert_eq!(price_to_tick(dec!(0.531), dec!(0.01)), dec!(0.54));
+        assert_eq!(price_to_tick(dec!(0.53), dec!(0.01)), dec!(0.53));
+        assert_eq!(price_to_tick(dec!(0.9951), dec!(0.001)), dec!(0.996));
+        assert_eq!(price_to_tick(dec!(0.995), dec!(0.01)), dec!(0.99));
+        assert_eq!(price_to_tick(dec!(0.537), Decimal::ZERO), dec!(0.537));
+    }
+
+    #[test]
+    fn normalize_buy_amount_rounds_to_cents() {
+        let market = market();
+        let amount = normalize_buy_amount(dec!(3.456), dec!(0.5), &market, Rounding::Down, dec!(4));
+        assert_eq!(amount, Ok(dec!(3.45)));
+        let amount = normalize_buy_amount(dec!(3.451), dec!(0.5), &market, Rounding::Up, dec!(4));
+        assert_eq!(amount, Ok(dec!(3.46)));
+    }
+
+    #[test]
+    fn normalize_buy_amount_below_minimum_size() {
+        // 5 shares at 0.5 cost at least 2.50 USDC.
+        let market = market();
+        assert_eq!(min_buy_amount(dec!(0.5), &market), dec!(2.5));
+        assert_eq!(
+            normalize_buy_amount(dec!(1), dec!(0.5), &market, Rounding::Down, dec!(4)),
+            Err(SkipReason::BelowMinimumSize {
+                amount: dec!(1),
+                min_amount: dec!(2.5)
+            })
+        );
+        assert_eq!(
+            normalize_buy_amount(dec!(1), dec!(0.5), &market, Rounding::Up, dec!(4)),
+            Ok(dec!(2.5))
+        );
+        assert!(normalize_buy_amount(dec!(1), dec!(0.5), &market, Rounding::Up, dec!(2)).is_err());
+    }
+
+    #[test]
+    fn copy_amount_is_clamped() {
+        let config = SizingConfig {
+            multiplier: dec!(0.5),
+            min_amount: dec!(1),
+            max_amount: dec!(4),
+            rounding: Rounding::Down,
+        };
+        assert_eq!(config.copy_amount(dec!(0.5), dec!(10)), dec!(2.5));
+        assert_eq!(config.copy_amount(dec!(0.5), dec!(1)), dec!(1));
+        assert_eq!(config.copy_amount(dec!(0.5), dec!(100)), dec!(4));
+    }
+}
@@ -0,0 +1,568 @@
+use chrono::Utc;
+use polymarket_client_sdk::types::PostOrderResponse;
+use rusqlite::{params, Connection, OptionalExtension as _};
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::path::Path;
+use std::str::FromStr as _;
+use std::sync::Mutex;
+
+use crate::attribution::{CopyFill, Position, TargetPerformance};
+use crate::dedup::TradeKey;
+use crate::orders::OrderIntent;
+use crate::util::Redemption;
+
+const DEFAULT_DB_PATH: &str = "state.db";
+
+/// Schema migrations, applied in order. The database's `user_version` is the number applied so
+/// far; append new steps, never edit released ones.
+const MIGRATIONS: &[&str] = &[INITIAL_SCHEMA];
+
+const INITIAL_SCHEMA: &str = "
+    CREATE TABLE processed_trades (
+        mode TEXT NOT NULL,
+        transaction_hash TEXT NOT NULL,
+        asset TEXT NOT NULL,
+        side TEXT NOT NULL,
+        size TEXT NOT NULL,
+        price TEXT NOT NULL,
+        seen_at INTEGER NOT NULL,
+        PRIMARY KEY (mode, transaction_hash, asset, side, size, price)
+    );
+    CREATE INDEX processed_trades_seen_at ON processed_trades (seen_at);
+
+    CREATE TABLE intents (
+        id INTEGER PRIMARY KEY,
+        target TEXT NOT NULL,
+        token_id TEXT NOT NULL,
+        requested TEXT NOT NULL,
+        spent TEXT NOT NULL,
+        shares TEXT NOT NULL,
+        state TEXT NOT NULL,
+        error TEXT,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+
+    CREATE TABLE orders (
+        order_id TEXT PRIMARY KEY,
+        intent_id INTEGER NOT NULL REFERENCES intents (id),
+        status TEXT NOT NULL,
+        making_amount TEXT NOT NULL,
+        taking_amount TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+
+    CREATE TABLE fills (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        timestamp TEXT NOT NULL,
+        target TEXT NOT NULL,
+        token_id TEXT NOT NULL,
+        condition_id TEXT NOT NULL,
+        target_price TEXT NOT NULL,
+        requested TEXT NOT NULL,
+        spent TEXT NOT NULL,
+        shares TEXT NOT NULL,
+        paper INTEGER NOT NULL
+    );
+
+    CREATE TABLE positions (
+        target TEXT NOT NULL,
+        paper INTEGER NOT NULL,
+        token_id TEXT NOT NULL,
+        condition_id TEXT NOT NULL,
+        shares TEXT NOT NULL,
+        cost TEXT NOT NULL,
+        settled INTEGER NOT NULL
+    );
+    CREATE INDEX positions_target ON positions (target, paper);
+
+    CREATE TABLE target_stats (
+        target TEXT NOT NULL,
+        paper INTEGER NOT NULL,
+        copies INTEGER NOT NULL,
+        requested TEXT NOT NULL,
+        spent TEXT NOT NULL,
+        slippage_cost TEXT NOT NULL,
+        realized_pnl TEXT NOT NULL,
+        unrealized_pnl TEXT NOT NULL,
+        peak_pnl TEXT NOT NULL,
+        max_drawdown TEXT NOT NULL,
+        losing_streak INTEGER NOT NULL,
+        paused TEXT,
+        PRIMARY KEY (target, paper)
+    );
+
+    CREATE TABLE risk_counters (
+        name TEXT PRIMARY KEY,
+        value TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+
+    CREATE TABLE redemptions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        condition_id TEXT NOT NULL,
+        title TEXT NOT NULL,
+        neg_risk INTEGER NOT NULL,
+        payout TEXT NOT NULL,
+        tx_hash TEXT NOT NULL,
+        redeemed_at TEXT NOT NULL
+    );
+
+    CREATE TABLE rotated_targets (
+        wallet TEXT PRIMARY KEY,
+        promoted_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE rotation_changes (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        wallet TEXT NOT NULL,
+        promoted INTEGER NOT NULL,
+        changed_at INTEGER NOT NULL
+    );
+";
+
+type StoreResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+fn decimal(value: String) -> rusqlite::Result<Decimal> {
+    Decimal::from_str(&value).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, e.into())
+    })
+}
+
+/// Embedded SQLite database holding everything the bot must remember across restarts: processed
+/// target trades, copy intents and their orders, fills, positions, per-target stats, risk
+/// counters and redemptions.
+pub struct Store {
+    conn: Mutex<Connection>,
+}
+
+impl Store {
+    /// Opens the database at `STATE_DB_PATH` (default `state.db`).
+    pub fn from_env() -> StoreResult<Self> {
+        let path = env::var("STATE_DB_PATH").unwrap_or_else(|_| DEFAULT_DB_PATH.to_string());
+        Self::open(Path::new(&path))
+    }
+
+    /// Opens or creates the database at `path` and applies pending migrations.
+    pub fn open(path: &Path) -> StoreResult<Self> {
+        let mut conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+
+        let version =
+            conn.pragma_query_value(None, "user_version", |row| row.get::<_, i64>(0))? as usize;
+        if version > MIGRATIONS.len() {
+            return Err(format!(
+                "{} has schema version {}, newer than this build supports ({})",
+                path.display(),
+                version,
+                MIGRATIONS.len()
+            )
+            .into());
+        }
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = conn.transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", (i + 1) as i64)?;
+            tx.commit()?;
+            println!("Applied state migration {} to {}", i + 1, path.display());
+        }
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Trade keys processed in `mode` at or after `since` (unix seconds), with when they were
+    /// seen.
+    pub fn processed_trades(&self, mode: &str, since: i64) -> StoreResult<Vec<(TradeKey, i64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT transaction_hash, asset, side, size, price, seen_at
+             FROM processed_trades WHERE mode = ?1 AND seen_at >= ?2",
+        )?;
+        let rows = statement.query_map(params![mode, since], |row| {
+            Ok((
+                TradeKey {
+                    transaction_hash: row.get(0)?,
+                    asset: row.get(1)?,
+                    side: row.get(2)?,
+                    size: row.get(3)?,
+                    price: row.get(4)?,
+                },
+                row.get(5)?,
+            ))
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Records `key` as processed in `mode` and forgets keys seen before `expire_before`.
+    pub fn insert_processed_trade(
+        &self,
+        mode: &str,
+        key: &TradeKey,
+        seen_at: i64,
+        expire_before: i64,
+    ) -> StoreResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO processed_trades
+             (mode, transaction_hash, asset, side, size, price, seen_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                mode,
+                key.transaction_hash,
+                key.asset,
+                key.side,
+                key.size,
+                key.price,
+                seen_at
+            ],
+        )?;
+        conn.execute(
+            "DELETE FROM processed_trades WHERE seen_at < ?1",
+            params![expire_before],
+        )?;
+        Ok(())
+    }
+
+    /// Highest intent id recorded so far, `0` if none.
+    pub fn last_intent_id(&self) -> StoreResult<u64> {
+        let conn = self.conn.lock().unwrap();
+        let id = conn.query_row("SELECT MAX(id) FROM intents", [], |row| {
+            row.get::<_, Option<i64>>(0)
+        })?;
+        Ok(id.unwrap_or(0) as u64)
+    }
+
+    /// Inserts or updates `intent`, together with the orders posted for it so far.
+    pub fn save_intent(
+        &self,
+        intent: &OrderIntent,
+        responses: &[PostOrderResponse],
+    ) -> StoreResult<()> {
+        let now = Utc::now().to_rfc3339();
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO intents
+             (id, target, token_id, requested, spent, shares, state, error, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)
+             ON CONFLICT (id) DO UPDATE SET
+                 spent = excluded.spent,
+                 shares = excluded.shares,
+                 state = excluded.state,
+                 error = excluded.error,
+                 updated_at = excluded.updated_at",
+            params![
+                intent.id as i64,
+                intent.target,
+                intent.token_id,
+                intent.requested.to_string(),
+                intent.spent.to_string(),
+                intent.shares.to_string(),
+                format!("{:?}", intent.state),
+                intent.failure.as_ref().map(ToString::to_string),
+                now
+            ],
+        )?;
+        for response in responses.iter().filter(|r| !r.order_id.is_empty()) {
+            tx.execute(
+                "INSERT OR REPLACE INTO orders
+                 (order_id, intent_id, status, making_amount, taking_amount, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    response.order_id,
+                    intent.id as i64,
+                    format!("{:?}", response.status),
+                    response.making_amount.to_string(),
+                    response.taking_amount.to_string(),
+                    now
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Marks intents a previous run left before `Done` or `Failed` as failed, returning
+    /// `(id, token id, state)` of each. Those stopped at `Posted` or later may have filled.
+    pub fn interrupt_unfinished_intents(&self) -> StoreResult<Vec<(u64, String, String)>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let unfinished = {
+            let mut statement = tx.prepare(
+                "SELECT id, token_id, state FROM intents WHERE state NOT IN ('Done', 'Failed')",
+            )?;
+            let rows = statement.query_map([], |row| {
+                Ok((row.get::<_, i64>(0)? as u64, row.get(1)?, row.get(2)?))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+        tx.execute(
+            "UPDATE intents SET state = 'Failed', error = 'interrupted by restart', updated_at = ?1
+             WHERE state NOT IN ('Done', 'Failed')",
+            params![Utc::now().to_rfc3339()],
+        )?;
+        tx.commit()?;
+        Ok(unfinished)
+    }
+
+    /// Appends `fill` to the fill history and replaces the target's stats and positions with
+    /// `performance`, atomically.
+    pub fn record_fill(&self, fill: &CopyFill, performance: &TargetPerformance) -> StoreResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO fills
+             (timestamp, target, token_id, condition_id, target_price, requested, spent, shares, paper)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                fill.timestamp.to_rfc3339(),
+                fill.target,
+                fill.token_id,
+                fill.condition_id,
+                fill.target_price.to_string(),
+                fill.requested.to_string(),
+                fill.spent.to_string(),
+                fill.shares.to_string(),
+                fill.paper
+            ],
+        )?;
+        save_target(&tx, &fill.target, fill.paper, performance)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Replaces the stored stats and positions of every target in `targets`, in paper or live
+    /// mode.
+    pub fn save_targets(
+        &self,
+        targets: &BTreeMap<String, TargetPerformance>,
+        paper: bool,
+    ) -> StoreResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for (target, performance) in targets {
+            save_target(&tx, target, paper, performance)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Stored paper or live stats and positions by target. `seen` starts at zero, as it counts
+    /// since startup.
+    pub fn targets(&self, paper: bool) -> StoreResult<BTreeMap<String, TargetPerformance>> {
+        let conn = self.conn.lock().unwrap();
+        let mut targets = BTreeMap::new();
+        let mut statement = conn.prepare(
+            "SELECT target, copies, requested, spent, slippage_cost, realized_pnl, unrealized_pnl,
+                    peak_pnl, max_drawdown, losing_streak, paused
+             FROM target_stats WHERE paper = ?1",
+        )?;
+        let rows = statement.query_map(params![paper], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                TargetPerformance {
+                    seen: 0,
+                    copies: row.get::<_, i64>(1)? as usize,
+                    requested: decimal(row.get(2)?)?,
+                    spent: decimal(row.get(3)?)?,
+                    slippage_cost: decimal(row.get(4)?)?,
+                    realized_pnl: decimal(row.get(5)?)?,
+                    unrealized_pnl: decimal(row.get(6)?)?,
+                    peak_pnl: decimal(row.get(7)?)?,
+                    max_drawdown: decimal(row.get(8)?)?,
+                    losing_streak: row.get::<_, i64>(9)? as usize,
+                    paused: row.get(10)?,
+                    positions: Vec::new(),
+                },
+            ))
+        })?;
+        for row in rows {
+            let (target, performance) = row?;
+            targets.insert(target, performance);
+        }
+
+        let mut statement = conn.prepare(
+            "SELECT target, token_id, condition_id, shares, cost, settled
+             FROM positions WHERE paper = ?1 ORDER BY rowid",
+        )?;
+        let rows = statement.query_map(params![paper], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                Position {
+                    token_id: row.get(1)?,
+                    condition_id: row.get(2)?,
+                    shares: decimal(row.get(3)?)?,
+                    cost: decimal(row.get(4)?)?,
+                    settled: row.get(5)?,
+                    unrealized: Decimal::ZERO,
+                },
+            ))
+        })?;
+        for row in rows {
+            let (target, position) = row?;
+            targets
+                .entry(target)
+                .or_insert_with(TargetPerformance::default)
+                .positions
+                .push(position);
+        }
+        Ok(targets)
+    }
+
+    /// Records the realized payout of a mined redemption.
+    pub fn record_redemption(&self, redemption: &Redemption) -> StoreResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO redemptions (condition_id, title, neg_risk, payout, tx_hash, redeemed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                redemption.condition_id,
+                redemption.title,
+                redemption.neg_risk,
+                redemption.payout.to_string(),
+                redemption.tx_hash.as_deref().unwrap_or_default(),
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Wallets auto-follow has promoted and not demoted since, in promotion order.
+    pub fn rotated_targets(&self) -> StoreResult<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement =
+            conn.prepare("SELECT wallet FROM rotated_targets ORDER BY promoted_at, rowid")?;
+        let rows = statement.query_map([], |row| row.get(0))?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// When auto-follow promoted or demoted a target at or after `since` (unix seconds), oldest
+    /// first.
+    pub fn rotation_changes(&self, since: i64) -> StoreResult<Vec<i64>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT changed_at FROM rotation_changes WHERE changed_at >= ?1 ORDER BY changed_at",
+        )?;
+        let rows = statement.query_map(params![since], |row| row.get(0))?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Records that auto-follow promoted, or demoted, `wallet` at `at` (unix seconds).
+    pub fn record_rotation(&self, wallet: &str, promoted: bool, at: i64) -> StoreResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO rotation_changes (wallet, promoted, changed_at) VALUES (?1, ?2, ?3)",
+            params![wallet, promoted, at],
+        )?;
+        if promoted {
+            tx.execute(
+                "INSERT OR REPLACE INTO rotated_targets (wallet, promoted_at) VALUES (?1, ?2)",
+                params![wallet, at],
+            )?;
+        } else {
+            tx.execute(
+                "DELETE FROM rotated_targets WHERE wallet = ?1",
+                params![wallet],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn set_counter(&self, name: &str, value: &str) -> StoreResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO risk_counters (name, value, updated_at) VALUES (?1, ?2, ?3)",
+            params![name, value, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    pub fn clear_counter(&self, name: &str) -> StoreResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM risk_counters WHERE name = ?1", params![name])?;
+        Ok(())
+    }
+
+    pub fn counter(&self, name: &str) -> StoreResult<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn
+            .query_row(
+                "SELECT value FROM risk_counters WHERE name = ?1",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Counters whose name starts with `prefix`, keyed by the rest of the name.
+    pub fn counters(&self, prefix: &str) -> StoreResult<HashMap<String, String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare("SELECT name, value FROM risk_counters")?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut counters = HashMap::new();
+        for row in rows {
+            let (name, value) = row?;
+            if let Some(name) = name.strip_prefix(prefix) {
+                counters.insert(name.to_string(), value);
+            }
+        }
+        Ok(counters)
+    }
+}
+
+fn save_target(
+    tx: &rusqlite::Transaction<'_>,
+    target: &str,
+    paper: bool,
+    performance: &TargetPerformance,
+) -> rusqlite::Result<()> {
+    tx.execute(
+        "INSERT OR REPLACE INTO target_stats
+         (target, paper, copies, requested, spent, slippage_cost, realized_pnl, unrealized_pnl,
+          peak_pnl, max_drawdown, losing_streak, paused)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            target,
+            paper,
+            performance.copies as i64,
+            performance.requested.to_string(),
+            performance.spent.to_string(),
+            performance.slippage_cost.to_string(),
+            performance.realized_pnl.to_string(),
+            performance.unrealized_pnl.to_string(),
+            performance.peak_pnl.to_string(),
+            performance.max_drawdown.to_string(),
+            performance.losing_streak as i64,
+            performance.paused
+        ],
+    )?;
+    tx.execute(
+        "DELETE FROM positions WHERE target = ?1 AND paper = ?2",
+        params![target, paper],
+    )?;
+    for position in &performance.positions {
+        tx.execute(
+            "INSERT INTO positions (target, paper, token_id, condition_id, shares, cost, settled)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                target,
+                paper,
+                position.token_id,
+                position.condition_id,
+                position.shares.to_string(),
+                position.cost.to_string(),
+                position.settled
+            ],
+        )?;
+    }
+    Ok(())
+}
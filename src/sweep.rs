@@ -0,0 +1,565 @@
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::str::FromStr;
+use std::thread;
+
+use crate::backtest::{
+    backtest_pipeline, pct, simulate, BacktestConfig, BacktestData, BacktestReport, FillModel,
+    TargetStats,
+};
+use crate::cli::CliArgs;
+
+const DEFAULT_TOP: usize = 20;
+
+/// One point of the parameter grid.
+#[derive(Debug, Clone)]
+struct Candidate {
+    label: String,
+    config: BacktestConfig,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum RankBy {
+    Pnl,
+    Roi,
+    HitRate,
+}
+
+impl RankBy {
+    fn from_args(args: &CliArgs) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        match args.get("rank-by").unwrap_or("pnl") {
+            "pnl" => Ok(RankBy::Pnl),
+            "roi" => Ok(RankBy::Roi),
+            "hit-rate" => Ok(RankBy::HitRate),
+            other => Err(format!("--rank-by must be pnl, roi or hit-rate, got '{}'", other).into()),
+        }
+    }
+
+    fn score(self, stats: &TargetStats) -> Decimal {
+        match self {
+            RankBy::Pnl => stats.pnl(),
+            RankBy::Roi => stats.roi().unwrap_or(Decimal::MIN),
+            RankBy::HitRate => stats.hit_rate().unwrap_or(Decimal::MIN),
+        }
+    }
+}
+
+fn opt<T: fmt::Display>(value: &Option<T>) -> String {
+    value
+        .as_ref()
+        .map_or_else(|| "-".to_string(), ToString::to_string)
+}
+
+/// Values of grid option `--name`, comma-separated, or `base` alone when it is not given. `-`
+/// stands for unset.
+fn grid<T>(
+    args: &CliArgs,
+    name: &str,
+    base: Option<T>,
+) -> Result<Vec<Option<T>>, Box<dyn std::error::Error + Send + Sync>>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let Some(raw) = args.get(name) else {
+        return Ok(vec![base]);
+    };
+    raw.split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| {
+            if v == "-" {
+                return Ok(None);
+            }
+            v.parse::<T>()
+                .map(Some)
+                .map_err(|e| format!("Invalid --{} value '{}': {}", name, v, e).into())
+        })
+        .collect()
+}
+
+/// Like [`grid`] for parameters that cannot be unset.
+fn required_grid<T>(
+    args: &CliArgs,
+    name: &str,
+    base: T,
+) -> Result<Vec<T>, Box<dyn std::error::Error + Send + Sync>>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    grid(args, name, Some(base))?
+        .into_iter()
+        .map(|v| v.ok_or_else(|| format!("--{} cannot be unset", name).into()))
+        .collect()
+}
+
+/// Crosses every candidate with every value, appending the label part `apply` returns.
+fn cross<T>(
+    candidates: Vec<Candidate>,
+    values: &[T],
+    apply: impl Fn(&mut BacktestConfig, &T) -> String,
+) -> Vec<Candidate> {
+    let apply = &apply;
+    candidates
+        .into_iter()
+        .flat_map(|candidate| {
+            values.iter().map(move |value| {
+                let mut next = candidate.clone();
+                let part = apply(&mut next.config, value);
+                next.label = if next.label.is_empty() {
+                    part
+                } else {
+                    format!("{} {}", next.label, part)
+                };
+                next
+            })
+        })
+        .collect()
+}
+
+/// Expands the grid options over the environment's pipeline. Points whose minimum order size
+/// exceeds the maximum are dropped.
+fn candidates(
+    args: &CliArgs,
+    base: BacktestConfig,
+    target_sets: &[Vec<String>],
+) -> Result<Vec<Candidate>, Box<dyn std::error::Error + Send + Sync>> {
+    let pipeline = &base.pipeline;
+    let multipliers = required_grid(args, "multiplier", pipeline.sizing.multiplier)?;
+    let min_amounts = required_grid(args, "min-usdc", pipeline.sizing.min_amount)?;
+    let max_amounts = required_grid(args, "max-usdc", pipeline.sizing.max_amount)?;
+    let min_prices = grid(args, "min-price", pipeline.filter.min_entry_price)?;
+    let max_prices = grid(args, "max-price", pipeline.filter.max_entry_price)?;
+    let max_ages = grid(args, "max-age-secs", pipeline.gates.max_trade_age_secs)?;
+
+    let mut candidates = vec![Candidate {
+        label: String::new(),
+        config: base,
+    }];
+    candidates = cross(candidates, &multipliers, |c, v| {
+        c.pipeline.sizing.multiplier = *v;
+        format!("mult={}", v)
+    });
+    candidates = cross(candidates, &min_amounts, |c, v| {
+        c.pipeline.sizing.min_amount = *v;
+        format!("min={}", v)
+    });
+    candidates = cross(candidates, &max_amounts, |c, v| {
+        c.pipeline.sizing.max_amount = *v;
+        format!("max={}", v)
+    });
+    candidates = cross(candidates, &min_prices, |c, v| {
+        c.pipeline.filter.min_entry_price = *v;
+        format!("minp={}", opt(v))
+    });
+    candidates = cross(candidates, &max_prices, |c, v| {
+        c.pipeline.filter.max_entry_price = *v;
+        format!("maxp={}", opt(v))
+    });
+    candidates = cross(candidates, &max_ages, |c, v| {
+        c.pipeline.gates.max_trade_age_secs = v.filter(|secs| *secs > 0);
+        format!("age={}", opt(&c.pipeline.gates.max_trade_age_secs))
+    });
+    let set_indices = (0..target_sets.len()).collect::<Vec<_>>();
+    candidates = cross(candidates, &set_indices, |c, i| {
+        c.targets = target_sets[*i].clone();
+        format!("set={}", i)
+    });
+
+    Ok(candidates
+        .into_iter()
+        .filter(|c| c.config.pipeline.sizing.min_amount <= c.config.pipeline.sizing.max_amount)
+        .collect())
+}
+
+/// Backtests every candidate over `window`, counting only copies settled before `settled_by`
+/// when given, spread across the available cores.
+fn evaluate(
+    data: &BacktestData,
+    candidates: &[Candidate],
+    window: Option<&Range<DateTime<Utc>>>,
+    settled_by: Option<DateTime<Utc>>,
+) -> Vec<BacktestReport> {
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let chunk_size = candidates.len().div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        let handles = candidates
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|c| simulate(data, &c.config, window, settled_by))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("backtest thread panicked"))
+            .collect()
+    })
+}
+
+/// Candidate indices ordered best first.
+fn rank(reports: &[BacktestReport], rank_by: RankBy) -> Vec<usize> {
+    let mut order = (0..reports.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| std::cmp::Reverse(rank_by.score(&reports[i].total)));
+    order
+}
+
+fn print_ranked(candidates: &[Candidate], reports: &[BacktestReport], rank_by: RankBy, top: usize) {
+    println!(
+        "{:>4} {:<64} {:>6} {:>6} {:>10} {:>10} {:>7} {:>9}",
+        "rank", "config", "copies", "hit%", "turnover", "pnl", "roi%", "max dd"
+    );
+    for (position, i) in rank(reports, rank_by).into_iter().take(top).enumerate() {
+        let stats = &reports[i].total;
+        println!(
+            "{:>4} {:<64} {:>6} {:>6} {:>10} {:>10} {:>7} {:>9}",
+            position + 1,
+            candidates[i].label,
+            stats.copies,
+            pct(stats.hit_rate()),
+            stats.turnover.round_dp(2).to_string(),
+            stats.pnl().round_dp(2).to_string(),
+            pct(stats.roi()),
+            stats.max_drawdown.round_dp(2).to_string()
+        );
+    }
+}
+
+/// One walk-forward step: the candidate picked on the train window and how it did on the test
+/// window.
+struct Fold {
+    train: Range<DateTime<Utc>>,
+    test: Range<DateTime<Utc>>,
+    best: usize,
+    trained: BacktestReport,
+    tested: BacktestReport,
+}
+
+/// Picks the best candidate on each train window and scores it on the test window that follows.
+/// Folds advance by the test length, so every test window is out of sample. The train ranking
+/// only counts copies settled before the train window ends, so the pick uses no later
+/// information.
+fn walk_forward(
+    data: &BacktestData,
+    candidates: &[Candidate],
+    rank_by: RankBy,
+    train: Duration,
+    test: Duration,
+) -> Vec<Fold> {
+    let (Some(first), Some(last)) = (data.trades.first(), data.trades.last()) else {
+        return Vec::new();
+    };
+
+    let mut folds = Vec::new();
+    let mut start = first.timestamp;
+    while start + train <= last.timestamp {
+        let train_window = start..start + train;
+        let test_window = start + train..start + train + test;
+        let mut reports = evaluate(
+            data,
+            candidates,
+            Some(&train_window),
+            Some(train_window.end),
+        );
+        let Some(&best) = rank(&reports, rank_by).first() else {
+            break;
+        };
+        let tested = simulate(data, &candidates[best].config, Some(&test_window), None);
+        folds.push(Fold {
+            train: train_window,
+            test: test_window,
+            best,
+            trained: reports.swap_remove(best),
+            tested,
+        });
+        start += test;
+    }
+    folds
+}
+
+fn print_walk_forward(candidates: &[Candidate], folds: &[Fold]) {
+    if folds.is_empty() {
+        println!("No complete train window to walk forward over");
+        return;
+    }
+    println!(
+        "{:>4} {:<23} {:<23} {:<64} {:>10} {:>10} {:>7}",
+        "fold", "train", "test", "best config", "train pnl", "test pnl", "roi%"
+    );
+    for (i, fold) in folds.iter().enumerate() {
+        println!(
+            "{:>4} {:<23} {:<23} {:<64} {:>10} {:>10} {:>7}",
+            i + 1,
+            format!(
+                "{}..{}",
+                fold.train.start.format("%Y-%m-%d"),
+                fold.train.end.format("%Y-%m-%d")
+            ),
+            format!(
+                "{}..{}",
+                fold.test.start.format("%Y-%m-%d"),
+                fold.test.end.format("%Y-%m-%d")
+            ),
+            candidates[fold.best].label,
+            fold.trained.total.pnl().round_dp(2).to_string(),
+            fold.tested.total.pnl().round_dp(2).to_string(),
+            pct(fold.tested.total.roi())
+        );
+    }
+    let out_of_sample = TargetStats::concat(folds.iter().map(|fold| &fold.tested));
+    println!(
+        "Out of sample: {} copies, turnover {} USDC, pnl {} USDC, roi {}%, hit rate {}%, \
+         max drawdown {} USDC",
+        out_of_sample.copies,
+        out_of_sample.turnover.round_dp(2),
+        out_of_sample.pnl().round_dp(2),
+        pct(out_of_sample.roi()),
+        pct(out_of_sample.hit_rate()),
+        out_of_sample.max_drawdown.round_dp(2)
+    );
+}
+
+/// `sweep [LOG] --target-sets "a,b;c" [--multiplier 0.5,1,2] [--min-usdc ..] [--max-usdc ..]
+/// [--min-price ..] [--max-price ..] [--max-age-secs ..] [--rank-by pnl|roi|hit-rate] [--top N]
+/// [--train-days N --test-days N]`
+pub async fn run(args: &CliArgs) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let target_sets = match args.get("target-sets") {
+        Some(raw) => raw
+            .split(';')
+            .map(|set| {
+                set.split(',')
+                    .map(|t| t.trim().to_lowercase())
+                    .filter(|t| !t.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|set| !set.is_empty())
+            .collect::<Vec<_>>(),
+        None => vec![args.list("targets")]
+            .into_iter()
+            .filter(|set| !set.is_empty())
+            .collect(),
+    };
+    if target_sets.is_empty() {
+        return Err("--target-sets or --targets is required".into());
+    }
+    let rank_by = RankBy::from_args(args)?;
+    let top = args.parse_or("top", DEFAULT_TOP)?;
+    let base = BacktestConfig {
+        targets: Vec::new(),
+        pipeline: backtest_pipeline()?,
+        fill: FillModel::from_args(args)?,
+    };
+    let candidates = candidates(args, base, &target_sets)?;
+    if candidates.is_empty() {
+        return Err("The parameter grid is empty".into());
+    }
+
+    let mut all_targets = target_sets.concat();
+    all_targets.sort();
+    all_targets.dedup();
+    let data = BacktestData::from_args(args, &all_targets).await?;
+
+    for (i, set) in target_sets.iter().enumerate() {
+        println!("set={}: {}", i, set.join(","));
+    }
+    println!(
+        "Sweeping {} configurations on {} threads",
+        candidates.len(),
+        thread::available_parallelism().map_or(1, NonZeroUsize::get)
+    );
+
+    match (args.get("train-days"), args.get("test-days")) {
+        (None, None) => {
+            let reports = evaluate(&data, &candidates, None, None);
+            print_ranked(&candidates, &reports, rank_by, top);
+        }
+        _ => {
+            let train_days = args.parse_or("train-days", 7i64)?;
+            let test_days = args.parse_or("test-days", 1i64)?;
+            if train_days <= 0 || test_days <= 0 {
+                return Err("--train-days and --test-days must be positive".into());
+            }
+            let folds = walk_forward(
+                &data,
+                &candidates,
+                rank_by,
+                Duration::days(train_days),
+                Duration::days(test_days),
+            );
+            print_walk_forward(&candidates, &folds);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::RecordedTrade;
+    use crate::filters::MarketFilter;
+    use crate::gates::GateConfig;
+    use crate::market_catalog::tests::market;
+    use crate::market_catalog::MarketInfo;
+    use crate::pipeline::Pipeline;
+    use crate::sizing::{Rounding, SizingConfig};
+    use crate::trade::TargetTrade;
+    use polymarket_client_sdk::types::Side;
+    use rust_decimal_macros::dec;
+    use std::collections::HashMap;
+
+    fn at(hours: i64) -> DateTime<Utc> {
+        DateTime::UNIX_EPOCH + Duration::hours(hours)
+    }
+
+    /// A market whose outcome "1" won, ending `end_hours` after the epoch.
+    fn resolved(condition_id: &str, end_hours: i64) -> MarketInfo {
+        let mut market = market();
+        market.condition_id = condition_id.to_string();
+        market.end_date = Some(at(end_hours));
+        market.closed = true;
+        market.outcomes[0].winner = true;
+        market
+    }
+
+    fn buy(wallet: &str, hours: i64, condition_id: &str, price: Decimal) -> RecordedTrade {
+        RecordedTrade {
+            wallet: wallet.to_string(),
+            name: String::new(),
+            timestamp: at(hours),
+            trade: TargetTrade {
+                asset: "1".to_string(),
+                condition_id: Some(condition_id.to_string()),
+                side: Side::Buy,
+                size: dec!(10),
+                price,
+            },
+        }
+    }
+
+    fn candidate(target: &str) -> Candidate {
+        Candidate {
+            label: target.to_string(),
+            config: BacktestConfig {
+                targets: vec![target.to_string()],
+                pipeline: Pipeline {
+                    gates: GateConfig {
+                        max_trade_age_secs: None,
+                        max_price_drift: None,
+                    },
+                    filter: MarketFilter::default(),
+                    sizing: SizingConfig {
+                        multiplier: Decimal::ONE,
+                        min_amount: Decimal::ONE,
+                        max_amount: dec!(4),
+                        rounding: Rounding::Down,
+                    },
+                },
+                fill: FillModel {
+                    latency_secs: 0,
+                    slippage: Decimal::ZERO,
+                },
+            },
+        }
+    }
+
+    fn report(turnover: Decimal, value: Decimal, settlements: &[Decimal]) -> BacktestReport {
+        BacktestReport {
+            total: TargetStats {
+                copies: settlements.len(),
+                turnover,
+                value,
+                ..TargetStats::default()
+            },
+            settlements: settlements.to_vec(),
+            ..BacktestReport::default()
+        }
+    }
+
+    #[test]
+    fn rank_orders_best_first() {
+        let reports = [
+            report(dec!(10), dec!(12), &[]),
+            report(dec!(100), dec!(110), &[]),
+            report(dec!(10), dec!(5), &[]),
+        ];
+        assert_eq!(rank(&reports, RankBy::Pnl), [1, 0, 2]);
+        assert_eq!(rank(&reports, RankBy::Roi), [0, 1, 2]);
+    }
+
+    #[test]
+    fn walk_forward_picks_on_settled_train_copies_only() {
+        // "a" wins big on a market that only resolves long after the first train window; "b"
+        // wins less on one that resolves inside it.
+        let data = BacktestData {
+            trades: vec![
+                buy("b", 1, "early", dec!(0.5)),
+                buy("a", 2, "late", dec!(0.25)),
+                buy("b", 49, "early", dec!(0.5)),
+            ],
+            markets: HashMap::from([
+                ("early".to_string(), resolved("early", 12)),
+                ("late".to_string(), resolved("late", 1000)),
+            ]),
+        };
+        let candidates = [candidate("a"), candidate("b")];
+        let folds = walk_forward(
+            &data,
+            &candidates,
+            RankBy::Pnl,
+            Duration::hours(24),
+            Duration::hours(12),
+        );
+
+        // Folds start at the first trade, advance by the test length and stop once a train
+        // window would pass the last trade.
+        assert_eq!(folds.len(), 3);
+        for (i, fold) in folds.iter().enumerate() {
+            assert_eq!(fold.train.start, at(1 + 12 * i as i64));
+            assert_eq!(fold.train.end, fold.test.start);
+            assert_eq!(fold.test.end - fold.test.start, Duration::hours(12));
+        }
+
+        let first = &folds[0];
+        assert_eq!(candidates[first.best].label, "b");
+        assert_eq!(first.trained.total.pnl(), dec!(4));
+        // Without the settlement cut-off "a" would have ranked first on a 7.5 USDC win.
+        let unrestricted = simulate(&data, &candidates[0].config, Some(&first.train), None);
+        assert_eq!(unrestricted.total.pnl(), dec!(7.5));
+        let restricted = simulate(
+            &data,
+            &candidates[0].config,
+            Some(&first.train),
+            Some(first.train.end),
+        );
+        assert_eq!(restricted.total.copies, 0);
+        assert_eq!(restricted.total.pending, 1);
+    }
+
+    #[test]
+    fn out_of_sample_drawdown_spans_folds() {
+        let folds = [
+            report(dec!(10), dec!(12), &[dec!(5), dec!(-3)]),
+            report(dec!(10), dec!(7), &[dec!(-4), dec!(1)]),
+        ];
+        assert_eq!(drawdown_of(&folds[0]), dec!(3));
+        assert_eq!(drawdown_of(&folds[1]), dec!(4));
+
+        let total = TargetStats::concat(&folds);
+        assert_eq!(total.copies, 4);
+        assert_eq!(total.pnl(), dec!(-1));
+        // Equity runs 5, 2, -2, -1: a 7 USDC fall from the first fold's peak.
+        assert_eq!(total.max_drawdown, dec!(7));
+    }
+
+    fn drawdown_of(report: &BacktestReport) -> Decimal {
+        TargetStats::concat([report]).max_drawdown
+    }
+}
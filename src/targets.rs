@@ -0,0 +1,92 @@
+use std::env;
+use std::sync::{Arc, Mutex};
+
+/// Wallet followed when `TARGET_WALLETS` is not set.
+const DEFAULT_TARGET: &str = "afewfdzgre";
+
+#[derive(Default)]
+struct TargetSet {
+    pinned: Vec<String>,
+    rotated: Vec<String>,
+}
+
+/// The wallets being copied: pinned ones from `TARGET_WALLETS` plus any promoted by auto-follow.
+/// Targets are proxy wallets or names, matched case-insensitively.
+#[derive(Clone, Default)]
+pub struct Targets {
+    inner: Arc<Mutex<TargetSet>>,
+}
+
+impl Targets {
+    /// Reads `TARGET_WALLETS`, a comma-separated list of proxy wallets or names.
+    pub fn from_env() -> Self {
+        let mut pinned = env::var("TARGET_WALLETS")
+            .map(|v| {
+                v.split(',')
+                    .map(|s| s.trim().to_lowercase())
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        if pinned.is_empty() {
+            pinned.push(DEFAULT_TARGET.to_string());
+        }
+        println!("Following {}", pinned.join(", "));
+        Self::new(pinned)
+    }
+
+    /// Targets with `pinned` (lowercased proxy wallets or names) and none rotated in.
+    pub fn new(pinned: Vec<String>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(TargetSet {
+                pinned,
+                rotated: Vec::new(),
+            })),
+        }
+    }
+
+    /// The target an `activity/trades` payload belongs to, if any.
+    pub fn matches(&self, payload: &serde_json::Value) -> Option<String> {
+        let field = |name: &str| {
+            payload
+                .get(name)
+                .and_then(|v| v.as_str())
+                .map(str::to_lowercase)
+        };
+        let (wallet, name) = (field("proxyWallet"), field("name"));
+        let set = self.inner.lock().unwrap();
+        set.pinned
+            .iter()
+            .chain(&set.rotated)
+            .find(|t| wallet.as_ref() == Some(*t) || name.as_ref() == Some(*t))
+            .cloned()
+    }
+
+    /// Whether the trader with proxy `wallet` and display `name` is pinned. Pinned targets may be
+    /// either, so a pinned name covers the wallet trading under it.
+    pub fn is_pinned(&self, wallet: &str, name: &str) -> bool {
+        let (wallet, name) = (wallet.to_lowercase(), name.to_lowercase());
+        self.inner
+            .lock()
+            .unwrap()
+            .pinned
+            .iter()
+            .any(|t| *t == wallet || (!name.is_empty() && *t == name))
+    }
+
+    /// Targets added by auto-follow.
+    pub fn rotated(&self) -> Vec<String> {
+        self.inner.lock().unwrap().rotated.clone()
+    }
+
+    pub fn promote(&self, target: &str) {
+        let mut set = self.inner.lock().unwrap();
+        if !set.rotated.iter().any(|t| t == target) {
+            set.rotated.push(target.to_string());
+        }
+    }
+
+    pub fn demote(&self, target: &str) {
+        self.inner.lock().unwrap().rotated.retain(|t| t != target);
+    }
+}
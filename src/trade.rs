@@ -0,0 +1,186 @@
+use polymarket_client_sdk::types::Side;
+use rust_decimal::Decimal;
+use std::fmt;
+use std::str::FromStr as _;
+
+/// A monetary or trade field that could not be read from a payload.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TradeParseError {
+    Missing(&'static str),
+    Invalid { field: &'static str, value: String },
+}
+
+impl fmt::Display for TradeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TradeParseError::Missing(field) => write!(f, "missing field `{}`", field),
+            TradeParseError::Invalid { field, value } => {
+                write!(f, "invalid value for `{}`: {}", field, value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TradeParseError {}
+
+/// Parses a decimal from a JSON string or number.
+///
+/// Numbers are read from their JSON text rather than through `as_f64`, so `0.53` becomes exactly
+/// `0.53`; exponent notation is accepted. Returns `None` for anything else, including `NaN`-like
+/// strings.
+pub fn parse_decimal(value: &serde_json::Value) -> Option<Decimal> {
+    let text = match value {
+        serde_json::Value::Number(n) => n.to_string(),
+        serde_json::Value::String(s) => s.trim().to_string(),
+        _ => return None,
+    };
+    Decimal::from_str(&text)
+        .or_else(|_| Decimal::from_scientific(&text))
+        .ok()
+}
+
+/// Reads `field` from `payload` as a decimal, distinguishing a missing field from a malformed one.
+pub fn decimal_field(
+    payload: &serde_json::Value,
+    field: &'static str,
+) -> Result<Decimal, TradeParseError> {
+    let value = match payload.get(field) {
+        None | Some(serde_json::Value::Null) => return Err(TradeParseError::Missing(field)),
+        Some(value) => value,
+    };
+    parse_decimal(value).ok_or_else(|| TradeParseError::Invalid {
+        field,
+        value: value.to_string(),
+    })
+}
+
+/// The payload's trade timestamp in milliseconds. The feed sends seconds, but milliseconds are
+/// accepted too, as a number or a string.
+pub fn payload_timestamp_ms(payload: &serde_json::Value) -> Option<i64> {
+    payload
+        .get("timestamp")
+        .and_then(|v| v.as_i64().or_else(|| v.as_str()?.parse().ok()))
+        .map(|ts| if ts < 10_000_000_000 { ts * 1000 } else { ts })
+}
+
+fn str_field<'a>(
+    payload: &'a serde_json::Value,
+    field: &'static str,
+) -> Result<&'a str, TradeParseError> {
+    match payload.get(field) {
+        None | Some(serde_json::Value::Null) => Err(TradeParseError::Missing(field)),
+        Some(value) => value.as_str().ok_or_else(|| TradeParseError::Invalid {
+            field,
+            value: value.to_string(),
+        }),
+    }
+}
+
+/// The fields of an `activity/trades` payload that the copy path acts on.
+#[derive(Debug, Clone)]
+pub struct TargetTrade {
+    pub asset: String,
+    pub condition_id: Option<String>,
+    pub side: Side,
+    pub size: Decimal,
+    pub price: Decimal,
+}
+
+impl TargetTrade {
+    pub fn from_payload(payload: &serde_json::Value) -> Result<Self, TradeParseError> {
+        let side = match str_field(payload, "side")?.to_uppercase().as_str() {
+            "BUY" => Side::Buy,
+            "SELL" => Side::Sell,
+            other => {
+                return Err(TradeParseError::Invalid {
+                    field: "side",
+                    value: other.to_string(),
+                })
+            }
+        };
+
+        let size = decimal_field(payload, "size")?;
+        if size <= Decimal::ZERO {
+            return Err(TradeParseError::Invalid {
+                field: "size",
+                value: size.to_string(),
+            });
+        }
+        let price = decimal_field(payload, "price")?;
+        if price <= Decimal::ZERO || price > Decimal::ONE {
+            return Err(TradeParseError::Invalid {
+                field: "price",
+                value: price.to_string(),
+            });
+        }
+
+        Ok(Self {
+            asset: str_field(payload, "asset")?.to_string(),
+            condition_id: str_field(payload, "conditionId").ok().map(str::to_string),
+            side,
+            size,
+            price,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use serde_json::json;
+
+    #[test]
+    fn parse_decimal_keeps_exact_values() {
+        assert_eq!(parse_decimal(&json!(0.53)), Some(dec!(0.53)));
+        assert_eq!(parse_decimal(&json!(" 12.5 ")), Some(dec!(12.5)));
+        assert_eq!(parse_decimal(&json!("1e-2")), Some(dec!(0.01)));
+        assert_eq!(parse_decimal(&json!(3)), Some(dec!(3)));
+        assert_eq!(parse_decimal(&json!("NaN")), None);
+        assert_eq!(parse_decimal(&json!(true)), None);
+        assert_eq!(parse_decimal(&json!(null)), None);
+    }
+
+    #[test]
+    fn from_payload_validates_fields() {
+        let payload = json!({
+            "asset": "1",
+            "conditionId": "0xcondition",
+            "side": "buy",
+            "size": "10",
+            "price": 0.53,
+        });
+        let trade = TargetTrade::from_payload(&payload).unwrap();
+        assert_eq!(trade.side, Side::Buy);
+        assert_eq!((trade.size, trade.price), (dec!(10), dec!(0.53)));
+
+        let mut missing = payload.clone();
+        missing.as_object_mut().unwrap().remove("size");
+        assert_eq!(
+            TargetTrade::from_payload(&missing).unwrap_err(),
+            TradeParseError::Missing("size")
+        );
+        let mut invalid = payload.clone();
+        invalid["price"] = json!(1.5);
+        assert_eq!(
+            TargetTrade::from_payload(&invalid).unwrap_err(),
+            TradeParseError::Invalid {
+                field: "price",
+                value: "1.5".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn payload_timestamps_are_read_as_milliseconds() {
+        assert_eq!(
+            payload_timestamp_ms(&json!({"timestamp": 1_700_000_000})),
+            Some(1_700_000_000_000)
+        );
+        assert_eq!(
+            payload_timestamp_ms(&json!({"timestamp": "1700000000123"})),
+            Some(1_700_000_000_123)
+        );
+        assert_eq!(payload_timestamp_ms(&json!({})), None);
+    }
+}
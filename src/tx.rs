@@ -0,0 +1,431 @@
+use alloy::contract::{CallBuilder, CallDecoder, RawCallBuilder};
+use alloy::network::{ReceiptResponse as _, TransactionBuilder as _};
+use alloy::primitives::{Address, B256};
+use alloy::providers::{PendingTransactionError, Provider, WatchTxError};
+use alloy::rpc::types::TransactionReceipt;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::config::{env_opt, env_parse};
+use crate::funder::Funder;
+
+/// How often a mined transaction is checked while it waits for confirmations.
+const CONFIRMATION_POLL: Duration = Duration::from_secs(2);
+
+/// Fee caps, gas margin and receipt handling for on-chain transactions.
+#[derive(Debug, Clone)]
+pub struct GasPolicy {
+    /// Ceiling on `maxFeePerGas`, in wei, including after replacements.
+    pub max_fee: u128,
+    /// Ceiling on `maxPriorityFeePerGas`, in wei.
+    pub max_priority_fee: u128,
+    /// Floor on `maxPriorityFeePerGas`, in wei; Polygon validators drop lower tips.
+    pub min_priority_fee: u128,
+    /// Percentage added to the estimated gas limit.
+    pub gas_margin_pct: u64,
+    /// How long to wait for a receipt before replacing the transaction.
+    pub receipt_timeout: Duration,
+    /// Blocks the transaction must be buried under before it counts as mined.
+    pub confirmations: u64,
+    /// Replacements sent for a stuck transaction before giving up.
+    pub max_replacements: u32,
+    /// Percentage each replacement raises both fees by.
+    pub replacement_bump_pct: u64,
+}
+
+impl Default for GasPolicy {
+    fn default() -> Self {
+        Self {
+            max_fee: gwei(500),
+            max_priority_fee: gwei(100),
+            min_priority_fee: gwei(30),
+            gas_margin_pct: 20,
+            receipt_timeout: Duration::from_secs(120),
+            confirmations: 1,
+            max_replacements: 3,
+            replacement_bump_pct: 15,
+        }
+    }
+}
+
+fn gwei(amount: u64) -> u128 {
+    amount as u128 * 1_000_000_000
+}
+
+impl GasPolicy {
+    /// Reads `GAS_MAX_FEE_GWEI`, `GAS_MAX_PRIORITY_FEE_GWEI`, `GAS_MIN_PRIORITY_FEE_GWEI`,
+    /// `GAS_LIMIT_MARGIN_PCT`, `TX_RECEIPT_TIMEOUT_SECS`, `TX_CONFIRMATIONS`,
+    /// `TX_MAX_REPLACEMENTS` and `TX_REPLACEMENT_BUMP_PCT`.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let defaults = Self::default();
+        let gwei_var =
+            |name: &str, default: u128| -> Result<u128, Box<dyn std::error::Error + Send + Sync>> {
+                match env_opt::<Decimal>(name)? {
+                    Some(gwei) => {
+                        let wei = gwei * Decimal::from(1_000_000_000u64);
+                        Ok(wei.trunc().to_string().parse()?)
+                    }
+                    None => Ok(default),
+                }
+            };
+        let policy = Self {
+            max_fee: gwei_var("GAS_MAX_FEE_GWEI", defaults.max_fee)?,
+            max_priority_fee: gwei_var("GAS_MAX_PRIORITY_FEE_GWEI", defaults.max_priority_fee)?,
+            min_priority_fee: gwei_var("GAS_MIN_PRIORITY_FEE_GWEI", defaults.min_priority_fee)?,
+            gas_margin_pct: env_parse("GAS_LIMIT_MARGIN_PCT", defaults.gas_margin_pct)?,
+            receipt_timeout: Duration::from_secs(env_parse(
+                "TX_RECEIPT_TIMEOUT_SECS",
+                defaults.receipt_timeout.as_secs(),
+            )?),
+            confirmations: env_parse("TX_CONFIRMATIONS", defaults.confirmations)?.max(1),
+            max_replacements: env_parse("TX_MAX_REPLACEMENTS", defaults.max_replacements)?,
+            replacement_bump_pct: env_parse(
+                "TX_REPLACEMENT_BUMP_PCT",
+                defaults.replacement_bump_pct,
+            )?,
+        };
+        if policy.min_priority_fee > policy.max_priority_fee
+            || policy.max_priority_fee > policy.max_fee
+        {
+            return Err(
+                "Gas caps must satisfy min priority fee <= max priority fee <= max fee".into(),
+            );
+        }
+        // Nodes reject replacements that raise fees by less than 10%.
+        if policy.replacement_bump_pct < 10 {
+            return Err("TX_REPLACEMENT_BUMP_PCT must be at least 10".into());
+        }
+        Ok(policy)
+    }
+
+    /// Estimated fees, clamped to the caps and floor.
+    fn clamp(&self, max_fee: u128, priority_fee: u128) -> (u128, u128) {
+        let priority_fee = priority_fee.clamp(self.min_priority_fee, self.max_priority_fee);
+        (max_fee.clamp(priority_fee, self.max_fee), priority_fee)
+    }
+
+    fn bump(&self, fee: u128) -> u128 {
+        fee * (100 + self.replacement_bump_pct as u128) / 100
+    }
+
+    /// Fees for replacing a transaction sent with `max_fee` and `priority_fee`: both bumped, the
+    /// max fee to at least `estimated_max_fee`, within the caps. `None` once the caps leave no
+    /// room to raise them.
+    fn replacement(
+        &self,
+        max_fee: u128,
+        priority_fee: u128,
+        estimated_max_fee: u128,
+    ) -> Option<(u128, u128)> {
+        let bumped = self.clamp(
+            self.bump(max_fee).max(estimated_max_fee),
+            self.bump(priority_fee),
+        );
+        (bumped != (max_fee, priority_fee)).then_some(bumped)
+    }
+}
+
+/// Sends transactions under a [`GasPolicy`], with nonces assigned locally so that concurrent
+/// approvals, redemptions and transfers from one wallet do not collide. Calls from a proxy or
+/// Safe [`Funder`] are executed through it, sent and paid for by its owner EOA.
+pub struct TxSender {
+    policy: GasPolicy,
+    funder: Funder,
+    /// Next nonce per sender, fetched from the pending block on first use.
+    nonces: Mutex<HashMap<Address, u64>>,
+    /// Held while a call goes through the smart wallet, whose own nonce is read when it is signed.
+    smart_wallet: Mutex<()>,
+}
+
+impl TxSender {
+    pub fn new(policy: GasPolicy, funder: Funder) -> Self {
+        Self {
+            policy,
+            funder,
+            nonces: Mutex::new(HashMap::new()),
+            smart_wallet: Mutex::new(()),
+        }
+    }
+
+    pub fn from_env(funder: Funder) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self::new(GasPolicy::from_env()?, funder))
+    }
+
+    /// The wallet that holds the funds calls are sent for.
+    pub fn funder(&self) -> &Funder {
+        &self.funder
+    }
+
+    async fn next_nonce<P: Provider>(
+        &self,
+        provider: &P,
+        from: Address,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let mut nonces = self.nonces.lock().await;
+        let nonce = match nonces.get(&from) {
+            Some(nonce) => *nonce,
+            None => provider.get_transaction_count(from).pending().await?,
+        };
+        nonces.insert(from, nonce + 1);
+        Ok(nonce)
+    }
+
+    /// Forgets the cached nonce of `from`, so the next transaction reads it from the chain again.
+    async fn reset_nonce(&self, from: Address) {
+        self.nonces.lock().await.remove(&from);
+    }
+
+    /// Sends `call` from `from` and waits for its receipt. When `from` is a proxy or Safe funder,
+    /// the call is wrapped for it and sent from its owner; such calls go one at a time.
+    pub async fn send<P: Provider, D: CallDecoder>(
+        &self,
+        call: CallBuilder<P, D>,
+        from: Address,
+    ) -> Result<TransactionReceipt, Box<dyn std::error::Error + Send + Sync>> {
+        if from != self.funder.address() || !self.funder.is_smart_wallet() {
+            return self.send_from(call, from).await;
+        }
+        let to = call.as_ref().to().ok_or("Contract call without a target")?;
+        let _guard = self.smart_wallet.lock().await;
+        let (target, data) = self
+            .funder
+            .wrap_call(&call.provider, to, call.calldata().clone())
+            .await?;
+        let wrapped = RawCallBuilder::new_raw(&call.provider, data).to(target);
+        self.send_from(wrapped, self.funder.signer_address()).await
+    }
+
+    /// Sends `call` from the EOA `from`. Gas is estimated with the policy's margin and fees are
+    /// capped. A transaction that is not mined within the receipt timeout is replaced at the same
+    /// nonce with bumped fees, up to the configured number of times. Returns an error if the
+    /// transaction reverted, or is still pending after the last replacement.
+    async fn send_from<P: Provider, D: CallDecoder>(
+        &self,
+        call: CallBuilder<P, D>,
+        from: Address,
+    ) -> Result<TransactionReceipt, Box<dyn std::error::Error + Send + Sync>> {
+        let call = call.from(from);
+        let gas = call.estimate_gas().await?;
+        let gas = gas + gas * self.policy.gas_margin_pct / 100;
+        let estimate = call.provider.estimate_eip1559_fees().await?;
+        let (mut max_fee, mut priority_fee) = self
+            .policy
+            .clamp(estimate.max_fee_per_gas, estimate.max_priority_fee_per_gas);
+        let nonce = self.next_nonce(&call.provider, from).await?;
+        let mut call = call.gas(gas).nonce(nonce);
+
+        let mut sent: Vec<B256> = Vec::new();
+        let mut replacement_error = None;
+        for attempt in 0..=self.policy.max_replacements {
+            call = call
+                .max_fee_per_gas(max_fee)
+                .max_priority_fee_per_gas(priority_fee);
+            let pending = match call.send().await {
+                Ok(pending) => pending,
+                // A replacement can race the original being mined; that receipt is found below.
+                Err(e) if !sent.is_empty() => {
+                    eprintln!("Error replacing tx at nonce {}: {}", nonce, e);
+                    replacement_error = Some(e.to_string());
+                    break;
+                }
+                Err(e) => {
+                    self.reset_nonce(from).await;
+                    return Err(e.into());
+                }
+            };
+            sent.push(*pending.tx_hash());
+            if attempt > 0 {
+                println!(
+                    "Replaced stuck tx at nonce {} with {} (max fee {} gwei, tip {} gwei)",
+                    nonce,
+                    pending.tx_hash(),
+                    max_fee / 1_000_000_000,
+                    priority_fee / 1_000_000_000
+                );
+            }
+            let watched = pending
+                .with_required_confirmations(self.policy.confirmations)
+                .with_timeout(Some(self.policy.receipt_timeout))
+                .get_receipt()
+                .await;
+            match watched {
+                Ok(receipt) => return checked(receipt),
+                Err(PendingTransactionError::TxWatcher(WatchTxError::Timeout)) => {}
+                Err(e) => return Err(e.into()),
+            }
+            if let Some(receipt) = self.mined(&call.provider, &sent).await? {
+                return checked(receipt);
+            }
+            let Some(bumped) =
+                self.policy
+                    .replacement(max_fee, priority_fee, estimate.max_fee_per_gas)
+            else {
+                break;
+            };
+            (max_fee, priority_fee) = bumped;
+        }
+        if let Some(receipt) = self.mined(&call.provider, &sent).await? {
+            return checked(receipt);
+        }
+        self.reset_nonce(from).await;
+        Err(format!(
+            "tx at nonce {} not mined after {} attempts (last {}){}",
+            nonce,
+            sent.len(),
+            sent.last().map(|h| h.to_string()).unwrap_or_default(),
+            replacement_error
+                .map(|e| format!("; replacement failed: {}", e))
+                .unwrap_or_default()
+        )
+        .into())
+    }
+
+    /// The receipt of whichever of `hashes` was mined, once it has the policy's confirmations.
+    /// `None` if none was mined. A receipt with fewer confirmations is waited on for up to the
+    /// receipt timeout, and is an error if it is still short of them then.
+    async fn mined<P: Provider>(
+        &self,
+        provider: &P,
+        hashes: &[B256],
+    ) -> Result<Option<TransactionReceipt>, Box<dyn std::error::Error + Send + Sync>> {
+        let deadline = Instant::now() + self.policy.receipt_timeout;
+        loop {
+            let mut found = None;
+            for hash in hashes {
+                if let Some(receipt) = provider.get_transaction_receipt(*hash).await? {
+                    found = Some(receipt);
+                    break;
+                }
+            }
+            let Some(receipt) = found else {
+                return Ok(None);
+            };
+            let block = receipt.block_number().unwrap_or_default();
+            let confirmations = (provider.get_block_number().await? + 1).saturating_sub(block);
+            if confirmations >= self.policy.confirmations {
+                return Ok(Some(receipt));
+            }
+            if Instant::now() >= deadline {
+                return Err(format!(
+                    "tx {} has {} of {} confirmations",
+                    receipt.transaction_hash(),
+                    confirmations,
+                    self.policy.confirmations
+                )
+                .into());
+            }
+            tokio::time::sleep(CONFIRMATION_POLL).await;
+        }
+    }
+}
+
+fn checked(
+    receipt: TransactionReceipt,
+) -> Result<TransactionReceipt, Box<dyn std::error::Error + Send + Sync>> {
+    if !receipt.status() {
+        return Err(format!("tx {} reverted", receipt.transaction_hash()).into());
+    }
+    Ok(receipt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::providers::ProviderBuilder;
+    use alloy::transports::mock::Asserter;
+    use polymarket_client_sdk::types::SignatureType;
+
+    #[test]
+    fn estimates_are_clamped_to_caps_and_floor() {
+        let policy = GasPolicy::default();
+        assert_eq!(policy.clamp(gwei(200), gwei(10)), (gwei(200), gwei(30)));
+        assert_eq!(policy.clamp(gwei(900), gwei(150)), (gwei(500), gwei(100)));
+        // The max fee never drops below the tip it has to cover.
+        assert_eq!(policy.clamp(gwei(20), gwei(40)), (gwei(40), gwei(40)));
+    }
+
+    #[test]
+    fn replacements_bump_fees_until_the_caps() {
+        let policy = GasPolicy::default();
+        assert_eq!(
+            policy.replacement(gwei(100), gwei(40), gwei(90)),
+            Some((gwei(115), gwei(46)))
+        );
+        // A higher current estimate wins over the bump.
+        assert_eq!(
+            policy.replacement(gwei(100), gwei(40), gwei(200)),
+            Some((gwei(200), gwei(46)))
+        );
+        assert_eq!(
+            policy.replacement(gwei(480), gwei(95), gwei(100)),
+            Some((gwei(500), gwei(100)))
+        );
+        assert_eq!(policy.replacement(gwei(500), gwei(100), gwei(100)), None);
+    }
+
+    /// A sender that needs 3 confirmations and does not wait for them.
+    fn sender() -> TxSender {
+        let signer = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+            .parse()
+            .unwrap();
+        let policy = GasPolicy {
+            confirmations: 3,
+            receipt_timeout: Duration::ZERO,
+            ..GasPolicy::default()
+        };
+        TxSender::new(
+            policy,
+            Funder::new(signer, SignatureType::Eoa, None).unwrap(),
+        )
+    }
+
+    fn receipt(block: u64) -> serde_json::Value {
+        serde_json::json!({
+            "transactionHash": B256::repeat_byte(1),
+            "transactionIndex": "0x0",
+            "blockHash": B256::repeat_byte(2),
+            "blockNumber": format!("{:#x}", block),
+            "from": Address::repeat_byte(3),
+            "to": Address::repeat_byte(4),
+            "gasUsed": "0x5208",
+            "effectiveGasPrice": "0x1",
+            "cumulativeGasUsed": "0x5208",
+            "contractAddress": null,
+            "logs": [],
+            "logsBloom": format!("0x{}", "0".repeat(512)),
+            "type": "0x2",
+            "status": "0x1",
+        })
+    }
+
+    /// A provider answering a receipt lookup with `receipt`, then the block number with `head`.
+    fn chain(receipt: serde_json::Value, head: u64) -> impl Provider {
+        let asserter = Asserter::new();
+        asserter.push_success(&receipt);
+        asserter.push_success(&format!("{:#x}", head));
+        ProviderBuilder::new().connect_mocked_client(asserter)
+    }
+
+    #[tokio::test]
+    async fn mined_receipts_need_the_configured_confirmations() {
+        let sender = sender();
+        let hashes = [B256::repeat_byte(1)];
+        // Mined in block 10 with the head at 12: blocks 10, 11 and 12 are 3 confirmations.
+        let buried = sender.mined(&chain(receipt(10), 12), &hashes).await;
+        assert!(buried.unwrap().is_some());
+
+        let shallow = sender.mined(&chain(receipt(10), 11), &hashes).await;
+        assert!(shallow
+            .unwrap_err()
+            .to_string()
+            .contains("has 2 of 3 confirmations"));
+
+        let pending = sender
+            .mined(&chain(serde_json::Value::Null, 11), &hashes)
+            .await;
+        assert!(pending.unwrap().is_none());
+    }
+}
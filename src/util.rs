@@ -1,14 +1,40 @@
-use alloy::primitives::{Address, U256, address};
+use alloy::network::ReceiptResponse as _;
+use alloy::primitives::{Address, B256, U256, address};
 use alloy::providers::Provider;
+use alloy::signers::Signer as _;
+use alloy::signers::local::LocalSigner;
 use alloy::sol;
-use polymarket_client_sdk::ContractConfig;
+use polymarket_client_sdk::{ContractConfig, POLYGON, PRIVATE_KEY_VAR, contract_config};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fmt;
+use std::str::FromStr as _;
+use std::sync::Arc;
+use std::time::Duration;
 
-const USDC_ADDRESS: Address = address!("0x2791Bca1f2de4661ED88A30C99A7a9449Aa84174");
+use crate::attribution::{Attribution, Mark};
+use crate::cli::CliArgs;
+use crate::config::env_opt;
+use crate::funder::Funder;
+use crate::notifier::Notifier;
+use crate::rpc;
+use crate::store::Store;
+use crate::tx::TxSender;
+
+pub const USDC_ADDRESS: Address = address!("0x2791Bca1f2de4661ED88A30C99A7a9449Aa84174");
+pub const USDC_DECIMALS: u8 = 6;
+pub const NEG_RISK_ADAPTER_ADDRESS: Address = address!("0xd91E80cF2E7be2e162c6513ceD06f1dD0dA35296");
+const DEFAULT_DATA_API_HOST: &str = "https://data-api.polymarket.com";
+/// Positions the data API returns per page.
+const DATA_API_PAGE_SIZE: usize = 500;
 
 sol! {
     #[sol(rpc)]
     interface IERC20 {
         function approve(address spender, uint256 value) external returns (bool);
+        function transfer(address to, uint256 value) external returns (bool);
         function allowance(address owner, address spender) external view returns (uint256);
         function balanceOf(address account) external view returns (uint256);
         function decimals() external view returns (uint8);
@@ -18,97 +44,392 @@ sol! {
     interface IERC1155 {
         function setApprovalForAll(address operator, bool approved) external;
         function isApprovedForAll(address account, address operator) external view returns (bool);
+        function balanceOf(address account, uint256 id) external view returns (uint256);
+        function safeBatchTransferFrom(address from, address to, uint256[] ids, uint256[] values, bytes data) external;
     }
-}
 
-pub async fn set_all_approvals<P: Provider>(
-    provider: &P,
-    owner: Address,
-    config: &ContractConfig,
-    neg_risk_config: &ContractConfig,
-    allowance_amount: U256,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let token = IERC20::new(USDC_ADDRESS, provider.clone());
-    let ctf = IERC1155::new(config.conditional_tokens, provider.clone());
-
-    println!("Approving USDC allowances for address: {:?}", owner);
-    println!("USDC Contract: {:?}", USDC_ADDRESS);
-    println!("ConditionalTokens Contract: {:?}", config.conditional_tokens);
-    println!("Exchange Contract: {:?}", config.exchange);
-    println!("NegRisk Exchange Contract: {:?}", neg_risk_config.exchange);
-    
-    let balance = token.balanceOf(owner).call().await?;
-    let decimals = token.decimals().call().await?;
-    
-    let balance_formatted = format_balance(balance, decimals);
-    println!("\n💰 USDC Balance: {} USDC", balance_formatted);
-    println!("📊 Available for trading: {} USDC\n", balance_formatted);
-
-    let ctf_allowance = token.allowance(owner, config.conditional_tokens).call().await?;
-    if ctf_allowance < allowance_amount {
-        println!("Current CTF allowance: {}, setting to {}...", ctf_allowance, allowance_amount);
-        approve(&token, config.conditional_tokens, allowance_amount).await?;
-        println!("✅ USDC approved for ConditionalTokens contract");
-    } else {
-        println!("✅ USDC already approved for ConditionalTokens contract ({})", ctf_allowance);
+    #[sol(rpc)]
+    interface IConditionalTokens {
+        function splitPosition(address collateralToken, bytes32 parentCollectionId, bytes32 conditionId, uint256[] partition, uint256 amount) external;
+        function mergePositions(address collateralToken, bytes32 parentCollectionId, bytes32 conditionId, uint256[] partition, uint256 amount) external;
+        function redeemPositions(address collateralToken, bytes32 parentCollectionId, bytes32 conditionId, uint256[] indexSets) external;
+        function payoutDenominator(bytes32 conditionId) external view returns (uint256);
+        function payoutNumerators(bytes32 conditionId, uint256 index) external view returns (uint256);
+        function balanceOf(address owner, uint256 id) external view returns (uint256);
     }
 
-    let is_ctf_approved = ctf.isApprovedForAll(owner, config.conditional_tokens).call().await?;
-    if !is_ctf_approved {
-        println!("Approving ConditionalTokens for ConditionalTokens contract...");
-        set_approval_for_all(&ctf, config.conditional_tokens, true).await?;
-        println!("✅ ConditionalTokens approved for ConditionalTokens contract");
-    } else {
-        println!("✅ ConditionalTokens already approved for ConditionalTokens contract");
+    #[sol(rpc)]
+    interface INegRiskAdapter {
+        function splitPosition(bytes32 conditionId, uint256 amount) external;
+        function mergePositions(bytes32 conditionId, uint256 amount) external;
+        function convertPositions(bytes32 marketId, uint256 indexSet, uint256 amount) external;
+        function redeemPositions(bytes32 conditionId, uint256[] amounts) external;
     }
+}
 
-    let exchange_allowance = token.allowance(owner, config.exchange).call().await?;
-    if exchange_allowance < allowance_amount {
-        println!("Current Exchange allowance: {}, setting to {}...", exchange_allowance, allowance_amount);
-        approve(&token, config.exchange, allowance_amount).await?;
-        println!("✅ USDC approved for Exchange contract");
-    } else {
-        println!("✅ USDC already approved for Exchange contract ({})", exchange_allowance);
+/// How startup handles missing approvals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalMode {
+    /// Send whatever the plan calls for.
+    Apply,
+    /// Fail if anything is missing, without sending.
+    CheckOnly,
+    /// Print the plan without sending.
+    DryRun,
+}
+
+#[derive(Debug, Clone)]
+pub struct ApprovalConfig {
+    pub mode: ApprovalMode,
+    /// USDC allowance granted to each spender, in base units.
+    pub allowance: U256,
+    /// Allowances below this are topped back up to `allowance`.
+    pub top_up_below: U256,
+}
+
+impl ApprovalConfig {
+    /// Reads `APPROVAL_MODE` (`apply` by default, `check` or `dry-run`), `APPROVAL_ALLOWANCE_USDC`
+    /// (unset grants an unlimited allowance) and `APPROVAL_TOP_UP_BELOW_USDC` (default half the
+    /// allowance).
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let mode = match env::var("APPROVAL_MODE")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "apply" => ApprovalMode::Apply,
+            "check" => ApprovalMode::CheckOnly,
+            "dry-run" => ApprovalMode::DryRun,
+            other => {
+                return Err(format!(
+                    "APPROVAL_MODE must be apply, check or dry-run, got '{}'",
+                    other
+                )
+                .into())
+            }
+        };
+        let usdc = |name: &str| -> Result<Option<U256>, Box<dyn std::error::Error + Send + Sync>> {
+            env_opt::<Decimal>(name)?.map(usdc_units).transpose()
+        };
+        let allowance = usdc("APPROVAL_ALLOWANCE_USDC")?.unwrap_or(U256::MAX);
+        let top_up_below = usdc("APPROVAL_TOP_UP_BELOW_USDC")?.unwrap_or(allowance / U256::from(2));
+        if top_up_below > allowance {
+            return Err(
+                "APPROVAL_TOP_UP_BELOW_USDC must not exceed APPROVAL_ALLOWANCE_USDC".into(),
+            );
+        }
+        Ok(Self {
+            mode,
+            allowance,
+            top_up_below,
+        })
     }
+}
 
-    let is_exchange_approved = ctf.isApprovedForAll(owner, config.exchange).call().await?;
-    if !is_exchange_approved {
-        println!("Approving ConditionalTokens for Exchange contract...");
-        set_approval_for_all(&ctf, config.exchange, true).await?;
-        println!("✅ ConditionalTokens approved for Exchange contract");
+fn format_allowance(amount: U256) -> String {
+    if amount == U256::MAX {
+        "unlimited".to_string()
     } else {
-        println!("✅ ConditionalTokens already approved for Exchange contract");
+        format!("{} USDC", format_balance(amount, USDC_DECIMALS))
     }
+}
 
-    let neg_risk_allowance = token.allowance(owner, neg_risk_config.exchange).call().await?;
-    if neg_risk_allowance < allowance_amount {
-        println!("Current NegRisk Exchange allowance: {}, setting to {}...", neg_risk_allowance, allowance_amount);
-        approve(&token, neg_risk_config.exchange, allowance_amount).await?;
-        println!("✅ USDC approved for NegRisk Exchange contract");
-    } else {
-        println!("✅ USDC already approved for NegRisk Exchange contract ({})", neg_risk_allowance);
+/// One approval transaction the wallet is missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApprovalChange {
+    /// Set the USDC allowance of `spender` from `current` to `target`.
+    Allowance {
+        spender: &'static str,
+        address: Address,
+        token: Address,
+        current: U256,
+        target: U256,
+    },
+    /// Approve, or revoke, `operator` for all of the wallet's conditional tokens.
+    Operator {
+        operator: &'static str,
+        address: Address,
+        token: Address,
+        approved: bool,
+    },
+}
+
+impl fmt::Display for ApprovalChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApprovalChange::Allowance {
+                spender,
+                address,
+                current,
+                target,
+                ..
+            } => write!(
+                f,
+                "USDC allowance for {} ({}): {} -> {}",
+                spender,
+                address,
+                format_allowance(*current),
+                format_allowance(*target)
+            ),
+            ApprovalChange::Operator {
+                operator,
+                address,
+                approved,
+                ..
+            } => write!(
+                f,
+                "ConditionalTokens setApprovalForAll for {} ({}): {}",
+                operator, address, approved
+            ),
+        }
     }
+}
 
-    let is_neg_risk_approved = ctf.isApprovedForAll(owner, neg_risk_config.exchange).call().await?;
-    if !is_neg_risk_approved {
-        println!("Approving ConditionalTokens for NegRisk Exchange contract...");
-        set_approval_for_all(&ctf, neg_risk_config.exchange, true).await?;
-        println!("✅ ConditionalTokens approved for NegRisk Exchange contract");
-    } else {
-        println!("✅ ConditionalTokens already approved for NegRisk Exchange contract");
+/// Approvals a wallet is missing, as computed by [`plan_approvals`].
+#[derive(Debug, Clone, Default)]
+pub struct ApprovalPlan {
+    pub changes: Vec<ApprovalChange>,
+}
+
+impl ApprovalPlan {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
     }
 
-    println!("All allowances approved successfully!");
+    pub fn print(&self) {
+        if self.is_empty() {
+            println!("✅ All approvals in place");
+        }
+        for change in &self.changes {
+            println!("- {}", change);
+        }
+    }
+}
 
-    let final_balance = token.balanceOf(owner).call().await?;
-    let final_balance_formatted = format_balance(final_balance, decimals);
-    println!("\n💰 Final USDC Balance: {} USDC", final_balance_formatted);
-    println!("📊 Available for trading: {} USDC\n", final_balance_formatted);
-    
+/// A contract the wallet must approve: to spend `collateral` and to move its `conditional_tokens`.
+pub struct Spender {
+    pub name: &'static str,
+    pub address: Address,
+    pub collateral: Address,
+    pub conditional_tokens: Address,
+}
+
+/// The approval matrix: the exchange of each config, plus the NegRiskAdapter that neg-risk splits,
+/// merges, conversions and redemptions go through. The ConditionalTokens contract itself gets no
+/// standing allowance; a standard split approves exactly the amount it needs.
+pub fn spenders(config: &ContractConfig, neg_risk_config: &ContractConfig) -> Vec<Spender> {
+    let spender = |name, address, config: &ContractConfig| Spender {
+        name,
+        address,
+        collateral: config.collateral,
+        conditional_tokens: config.conditional_tokens,
+    };
+    vec![
+        spender("Exchange", config.exchange, config),
+        spender(
+            "NegRisk Exchange",
+            neg_risk_config.exchange,
+            neg_risk_config,
+        ),
+        spender("NegRiskAdapter", NEG_RISK_ADAPTER_ADDRESS, neg_risk_config),
+    ]
+}
+
+/// Reads the current allowances and operator approvals of `owner` and returns what is missing.
+/// Sends nothing.
+pub async fn plan_approvals<P: Provider>(
+    provider: &P,
+    owner: Address,
+    config: &ContractConfig,
+    neg_risk_config: &ContractConfig,
+    approvals: &ApprovalConfig,
+) -> Result<ApprovalPlan, Box<dyn std::error::Error + Send + Sync>> {
+    let mut plan = ApprovalPlan::default();
+    for spender in spenders(config, neg_risk_config) {
+        let token = IERC20::new(spender.collateral, provider);
+        let current = token.allowance(owner, spender.address).call().await?;
+        if current < approvals.top_up_below {
+            plan.changes.push(ApprovalChange::Allowance {
+                spender: spender.name,
+                address: spender.address,
+                token: spender.collateral,
+                current,
+                target: approvals.allowance,
+            });
+        }
+        let ctf = IERC1155::new(spender.conditional_tokens, provider);
+        if !ctf.isApprovedForAll(owner, spender.address).call().await? {
+            plan.changes.push(ApprovalChange::Operator {
+                operator: spender.name,
+                address: spender.address,
+                token: spender.conditional_tokens,
+                approved: true,
+            });
+        }
+    }
+    Ok(plan)
+}
+
+/// Sends the transactions in `plan`, one at a time. Once each is mined, the allowance or approval
+/// is read back, and an error is returned if it did not take effect.
+pub async fn apply_approvals<P: Provider>(
+    provider: &P,
+    sender: &TxSender,
+    owner: Address,
+    plan: &ApprovalPlan,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    for change in &plan.changes {
+        let applied = match change {
+            ApprovalChange::Allowance {
+                address,
+                token,
+                target,
+                ..
+            } => {
+                let token = IERC20::new(*token, provider);
+                approve(sender, &token, owner, *address, *target).await?;
+                token.allowance(owner, *address).call().await? == *target
+            }
+            ApprovalChange::Operator {
+                address,
+                token,
+                approved,
+                ..
+            } => {
+                let ctf = IERC1155::new(*token, provider);
+                set_approval_for_all(sender, &ctf, owner, *address, *approved).await?;
+                ctf.isApprovedForAll(owner, *address).call().await? == *approved
+            }
+        };
+        if !applied {
+            return Err(format!("{} was mined but did not take effect", change).into());
+        }
+        println!("✅ {}", change);
+    }
     Ok(())
 }
+/// Reads every allowance and operator approval `owner` has granted to a Polymarket contract and
+/// returns the changes that revoke them. Besides the approval matrix, this covers the
+/// ConditionalTokens contract, which older versions of the bot and `split` approve to spend USDC.
+pub async fn plan_revocations<P: Provider>(
+    provider: &P,
+    owner: Address,
+    config: &ContractConfig,
+    neg_risk_config: &ContractConfig,
+) -> Result<ApprovalPlan, Box<dyn std::error::Error + Send + Sync>> {
+    let mut spenders = spenders(config, neg_risk_config);
+    spenders.push(Spender {
+        name: "ConditionalTokens",
+        address: config.conditional_tokens,
+        collateral: config.collateral,
+        conditional_tokens: config.conditional_tokens,
+    });
+    let mut plan = ApprovalPlan::default();
+    for spender in spenders {
+        let token = IERC20::new(spender.collateral, provider);
+        let current = token.allowance(owner, spender.address).call().await?;
+        if !current.is_zero() {
+            plan.changes.push(ApprovalChange::Allowance {
+                spender: spender.name,
+                address: spender.address,
+                token: spender.collateral,
+                current,
+                target: U256::ZERO,
+            });
+        }
+        let ctf = IERC1155::new(spender.conditional_tokens, provider);
+        if spender.address != spender.conditional_tokens
+            && ctf.isApprovedForAll(owner, spender.address).call().await?
+        {
+            plan.changes.push(ApprovalChange::Operator {
+                operator: spender.name,
+                address: spender.address,
+                token: spender.conditional_tokens,
+                approved: false,
+            });
+        }
+    }
+    Ok(plan)
+}
 
-fn format_balance(balance: U256, decimals: u8) -> String {
+/// Plans the approvals `owner` needs and handles them according to the configured mode.
+pub async fn ensure_approvals<P: Provider>(
+    provider: &P,
+    sender: &TxSender,
+    owner: Address,
+    config: &ContractConfig,
+    neg_risk_config: &ContractConfig,
+    approvals: &ApprovalConfig,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let token = IERC20::new(config.collateral, provider);
+    let balance = token.balanceOf(owner).call().await?;
+    println!(
+        "\n💰 USDC Balance of {:?}: {} USDC\n",
+        owner,
+        format_balance(balance, USDC_DECIMALS)
+    );
+
+    let plan = plan_approvals(provider, owner, config, neg_risk_config, approvals).await?;
+    println!("Approval plan ({:?} mode):", approvals.mode);
+    plan.print();
+    if plan.is_empty() {
+        return Ok(());
+    }
+    match approvals.mode {
+        ApprovalMode::Apply => apply_approvals(provider, sender, owner, &plan).await,
+        ApprovalMode::CheckOnly => Err(format!(
+            "{} approvals missing; set APPROVAL_MODE=apply or grant them manually",
+            plan.changes.len()
+        )
+        .into()),
+        ApprovalMode::DryRun => {
+            println!("Dry run: no approvals sent; orders may be rejected until they are granted");
+            Ok(())
+        }
+    }
+}
+
+/// Tops up USDC allowances that fell below the threshold, checking every `interval`. Only
+/// allowances are topped up; missing operator approvals are left to startup.
+pub async fn run_allowance_top_ups<P: Provider>(
+    provider: P,
+    sender: Arc<TxSender>,
+    owner: Address,
+    config: &'static ContractConfig,
+    neg_risk_config: &'static ContractConfig,
+    approvals: ApprovalConfig,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let plan = match plan_approvals(&provider, owner, config, neg_risk_config, &approvals).await
+        {
+            Ok(plan) => ApprovalPlan {
+                changes: plan
+                    .changes
+                    .into_iter()
+                    .filter(|change| matches!(change, ApprovalChange::Allowance { .. }))
+                    .collect(),
+            },
+            Err(e) => {
+                eprintln!("Error checking allowances: {}", e);
+                continue;
+            }
+        };
+        if plan.is_empty() {
+            continue;
+        }
+        println!("Topping up allowances:");
+        plan.print();
+        if let Err(e) = apply_approvals(&provider, &sender, owner, &plan).await {
+            eprintln!("Error topping up allowances: {}", e);
+        }
+    }
+}
+
+pub fn format_balance(balance: U256, decimals: u8) -> String {
     let balance_str = balance.to_string();
     let decimals_usize = decimals as usize;
     
@@ -134,35 +455,473 @@ fn format_balance(balance: U256, decimals: u8) -> String {
     }
 }
 
-async fn approve<P: Provider>(
+pub async fn approve<P: Provider>(
+    sender: &TxSender,
     usdc: &IERC20::IERC20Instance<P>,
+    owner: Address,
     spender: Address,
     amount: U256,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     println!("Calling USDC.approve({spender:?}, {amount})...");
 
-    let receipt = usdc.approve(spender, amount).send().await?.watch().await?;
+    let receipt = sender.send(usdc.approve(spender, amount), owner).await?;
 
-    println!("USDC approve tx mined: {receipt:?}");
+    println!("USDC approve tx mined: {:?}", receipt.transaction_hash);
 
     Ok(())
 }
 
 async fn set_approval_for_all<P: Provider>(
+    sender: &TxSender,
     ctf: &IERC1155::IERC1155Instance<P>,
+    owner: Address,
     operator: Address,
     approved: bool,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     println!("Calling CTF.setApprovalForAll({operator:?}, {approved})...");
 
-    let receipt = ctf
-        .setApprovalForAll(operator, approved)
-        .send()
-        .await?
-        .watch()
+    let receipt = sender
+        .send(ctf.setApprovalForAll(operator, approved), owner)
         .await?;
 
-    println!("CTF setApprovalForAll tx mined: {receipt:?}");
+    println!(
+        "CTF setApprovalForAll tx mined: {:?}",
+        receipt.transaction_hash
+    );
+
+    Ok(())
+}
+
+/// A resolved condition we hold shares in, and what redeeming it pays.
+#[derive(Debug, Clone)]
+pub struct Redemption {
+    pub condition_id: String,
+    pub title: String,
+    pub neg_risk: bool,
+    /// USDC paid out, from the on-chain payout vector and our balances.
+    pub payout: Decimal,
+    /// USDC paid per share of each redeemed token, by token id.
+    pub payouts: HashMap<String, Decimal>,
+    /// Hash of the mined redemption, `None` on a dry run.
+    pub tx_hash: Option<String>,
+}
+
+/// A position as reported by the Polymarket data API.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataPosition {
+    /// ERC1155 token id of the outcome, in decimal.
+    pub asset: String,
+    pub condition_id: String,
+    pub outcome_index: usize,
+    #[serde(default)]
+    pub negative_risk: bool,
+    #[serde(default)]
+    pub title: String,
+}
+
+/// Positions of `owner` known to the data API (`DATA_API_HOST`), optionally only the redeemable
+/// ones. Pages through the results until a short page comes back.
+pub async fn data_positions(
+    owner: Address,
+    redeemable: bool,
+) -> Result<Vec<DataPosition>, Box<dyn std::error::Error + Send + Sync>> {
+    let host = env::var("DATA_API_HOST").unwrap_or_else(|_| DEFAULT_DATA_API_HOST.to_string());
+    let client = reqwest::Client::new();
+    let mut positions = Vec::new();
+    loop {
+        let mut query = vec![
+            ("user", owner.to_string()),
+            ("limit", DATA_API_PAGE_SIZE.to_string()),
+            ("offset", positions.len().to_string()),
+        ];
+        if redeemable {
+            query.push(("redeemable", "true".to_string()));
+        }
+        let page = client
+            .get(format!("{}/positions", host.trim_end_matches('/')))
+            .query(&query)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Vec<DataPosition>>()
+            .await?;
+        let last = page.len() < DATA_API_PAGE_SIZE;
+        positions.extend(page);
+        if last {
+            return Ok(positions);
+        }
+    }
+}
+
+/// Positions of `owner` the data API reports as redeemable, grouped by condition id.
+async fn redeemable_positions(
+    owner: Address,
+) -> Result<BTreeMap<String, Vec<DataPosition>>, Box<dyn std::error::Error + Send + Sync>> {
+    let positions = data_positions(owner, true).await?;
+
+    let mut by_condition = BTreeMap::<String, Vec<DataPosition>>::new();
+    for position in positions {
+        by_condition
+            .entry(position.condition_id.clone())
+            .or_default()
+            .push(position);
+    }
+    Ok(by_condition)
+}
+
+fn usdc_amount(amount: U256) -> Decimal {
+    Decimal::from_str(&format_balance(amount, USDC_DECIMALS)).unwrap_or_default()
+}
+
+/// `amount` USDC, or shares, in the 6-decimal base units the contracts use.
+pub fn usdc_units(amount: Decimal) -> Result<U256, Box<dyn std::error::Error + Send + Sync>> {
+    if amount <= Decimal::ZERO {
+        return Err(format!("Amount must be positive, got {}", amount).into());
+    }
+    let units = (amount * Decimal::from(10u64.pow(USDC_DECIMALS as u32))).trunc();
+    Ok(U256::from_str_radix(&units.to_string(), 10)?)
+}
+
+/// Signer from `PRIVATE_KEY`, a provider that signs with it and the verified [`Funder`] it
+/// controls, for the on-chain commands.
+pub async fn wallet_provider(
+) -> Result<(impl Provider + Clone, Funder), Box<dyn std::error::Error + Send + Sync>> {
+    let private_key = env::var(PRIVATE_KEY_VAR).map_err(|_| "Need a private key")?;
+    let signer = LocalSigner::from_str(&private_key)?.with_chain_id(Some(POLYGON));
+    let funder = Funder::from_env(signer.clone())?;
+    let provider = rpc::connect(signer).await?;
+    funder.verify(&provider).await?;
+    Ok((provider, funder))
+}
+
+/// Redeems every resolved condition `owner` holds shares in: through the ConditionalTokens
+/// contract for standard markets, and through the NegRiskAdapter for neg-risk markets. Each
+/// redemption waits for its receipt. With `dry_run`, payouts are computed but nothing is sent.
+///
+/// A condition that fails to redeem is logged and skipped, so one bad market does not block the
+/// rest.
+pub async fn redeem_resolved<P: Provider>(
+    provider: &P,
+    sender: &TxSender,
+    owner: Address,
+    config: &ContractConfig,
+    dry_run: bool,
+) -> Result<Vec<Redemption>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut redemptions = Vec::new();
+    for (condition_id, positions) in redeemable_positions(owner).await? {
+        match redeem_condition(
+            provider,
+            sender,
+            owner,
+            config,
+            &condition_id,
+            &positions,
+            dry_run,
+        )
+        .await
+        {
+            Ok(Some(redemption)) => redemptions.push(redemption),
+            Ok(None) => {}
+            Err(e) => eprintln!("Error redeeming condition {}: {}", condition_id, e),
+        }
+    }
+    Ok(redemptions)
+}
+
+async fn redeem_condition<P: Provider>(
+    provider: &P,
+    sender: &TxSender,
+    owner: Address,
+    config: &ContractConfig,
+    condition_id: &str,
+    positions: &[DataPosition],
+    dry_run: bool,
+) -> Result<Option<Redemption>, Box<dyn std::error::Error + Send + Sync>> {
+    let ctf = IConditionalTokens::new(config.conditional_tokens, provider);
+    let condition = B256::from_str(condition_id)?;
+    let denominator = ctf.payoutDenominator(condition).call().await?;
+    if denominator.is_zero() {
+        // Reported redeemable before the resolution reached the chain.
+        return Ok(None);
+    }
+
+    let neg_risk = positions.iter().any(|p| p.negative_risk);
+    let mut amounts = vec![U256::ZERO; 2];
+    let mut payout = U256::ZERO;
+    let mut payouts = HashMap::new();
+    for position in positions {
+        let token_id = U256::from_str_radix(&position.asset, 10)?;
+        let balance = ctf.balanceOf(owner, token_id).call().await?;
+        let numerator = ctf
+            .payoutNumerators(condition, U256::from(position.outcome_index))
+            .call()
+            .await?;
+        if position.outcome_index >= amounts.len() {
+            amounts.resize(position.outcome_index + 1, U256::ZERO);
+        }
+        amounts[position.outcome_index] = balance;
+        payout += balance * numerator / denominator;
+        let per_share = Decimal::from_str(&numerator.to_string())?
+            / Decimal::from_str(&denominator.to_string())?;
+        payouts.insert(position.asset.clone(), per_share);
+    }
+    if amounts.iter().all(|amount| amount.is_zero()) {
+        return Ok(None);
+    }
+
+    let mut redemption = Redemption {
+        condition_id: condition_id.to_string(),
+        title: positions
+            .first()
+            .map(|p| p.title.clone())
+            .unwrap_or_default(),
+        neg_risk,
+        payout: usdc_amount(payout),
+        payouts,
+        tx_hash: None,
+    };
+    if dry_run {
+        return Ok(Some(redemption));
+    }
+
+    println!(
+        "Redeeming {} ({}) for {} USDC{}...",
+        redemption.title,
+        condition_id,
+        redemption.payout,
+        if neg_risk { " via NegRiskAdapter" } else { "" }
+    );
+    let receipt = if neg_risk {
+        let approval = IERC1155::new(config.conditional_tokens, provider);
+        if !approval
+            .isApprovedForAll(owner, NEG_RISK_ADAPTER_ADDRESS)
+            .call()
+            .await?
+        {
+            set_approval_for_all(sender, &approval, owner, NEG_RISK_ADAPTER_ADDRESS, true)
+                .await?;
+        }
+        let adapter = INegRiskAdapter::new(NEG_RISK_ADAPTER_ADDRESS, provider);
+        sender
+            .send(adapter.redeemPositions(condition, amounts), owner)
+            .await?
+    } else {
+        // Index sets 0b01 and 0b10: both outcomes of a binary condition.
+        let index_sets = (0..amounts.len()).map(|i| U256::from(1u64 << i)).collect();
+        let call = ctf.redeemPositions(config.collateral, B256::ZERO, condition, index_sets);
+        sender.send(call, owner).await?
+    };
+    redemption.tx_hash = Some(receipt.transaction_hash().to_string());
+    Ok(Some(redemption))
+}
+
+/// Redeems the resolved positions of `sender`'s funder every `interval`, recording each payout in
+/// `store`, settling the copied positions it closes in `attribution` and announcing it through
+/// `notifier`.
+pub async fn run_redemptions<P: Provider>(
+    provider: P,
+    sender: Arc<TxSender>,
+    config: &'static ContractConfig,
+    store: Arc<Store>,
+    attribution: Arc<Attribution>,
+    notifier: Notifier,
+    interval: Duration,
+) {
+    let owner = sender.funder().address();
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        match redeem_resolved(&provider, &sender, owner, config, false).await {
+            Ok(redemptions) => {
+                for redemption in &redemptions {
+                    record_redemption(&store, Some(&attribution), &notifier, redemption);
+                }
+            }
+            Err(e) => eprintln!("Error looking up redeemable positions: {}", e),
+        }
+    }
+}
+
+/// Records `redemption` in `store` and announces it. With `attribution`, also settles the copied
+/// positions it closes.
+pub fn record_redemption(
+    store: &Store,
+    attribution: Option<&Attribution>,
+    notifier: &Notifier,
+    redemption: &Redemption,
+) {
+    if let Err(e) = store.record_redemption(redemption) {
+        eprintln!(
+            "Error recording redemption of {}: {}",
+            redemption.condition_id, e
+        );
+    }
+    // Realizes the copied PnL now rather than when the market's resolution is next looked up.
+    if let Some(attribution) = attribution {
+        let marks = redemption
+            .payouts
+            .iter()
+            .map(|(token_id, payout)| (token_id.clone(), Mark::Resolved(*payout)))
+            .collect();
+        attribution.apply_marks(&marks);
+    }
+    notifier.notify(&format!(
+        "Redeemed {} USDC from {} ({})",
+        redemption.payout.round_dp(2),
+        redemption.title,
+        redemption.condition_id
+    ));
+}
 
+/// `redeem [--dry-run true|false]`: redeems every resolved position once and exits.
+pub async fn run_redeem(args: &CliArgs) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let dry_run = args.parse_or("dry-run", false)?;
+    let (provider, funder) = wallet_provider().await?;
+    let owner = funder.address();
+    let config = contract_config(POLYGON, false).ok_or("No contract config for Polygon")?;
+
+    let sender = TxSender::from_env(funder)?;
+    let redemptions = redeem_resolved(&provider, &sender, owner, config, dry_run).await?;
+    if dry_run {
+        for redemption in &redemptions {
+            println!(
+                "Would redeem {} USDC from {} ({}){}",
+                redemption.payout.round_dp(2),
+                redemption.title,
+                redemption.condition_id,
+                if redemption.neg_risk {
+                    " via NegRiskAdapter"
+                } else {
+                    ""
+                }
+            );
+        }
+    } else {
+        // Only the redemption rows are written. The copied positions are settled by the bot when
+        // it next looks up the markets' resolutions; saving our own attribution here would
+        // overwrite the target stats of a bot running on the same store.
+        let store = Store::from_env()?;
+        let notifier = Notifier::from_env();
+        for redemption in &redemptions {
+            record_redemption(&store, None, &notifier, redemption);
+        }
+    }
+    println!(
+        "{} {} conditions for {} USDC",
+        if dry_run { "Would redeem" } else { "Redeemed" },
+        redemptions.len(),
+        redemptions
+            .iter()
+            .map(|r| r.payout)
+            .sum::<Decimal>()
+            .round_dp(2)
+    );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Bytes;
+    use alloy::providers::ProviderBuilder;
+    use alloy::transports::mock::Asserter;
+
+    /// Queues `words` as the ABI-encoded results of successive `eth_call`s.
+    fn mock_calls(words: &[U256]) -> impl Provider {
+        let asserter = Asserter::new();
+        for word in words {
+            asserter.push_success(&Bytes::from(word.to_be_bytes::<32>()));
+        }
+        ProviderBuilder::new().connect_mocked_client(asserter)
+    }
+
+    fn configs() -> (&'static ContractConfig, &'static ContractConfig) {
+        (
+            contract_config(POLYGON, false).unwrap(),
+            contract_config(POLYGON, true).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn plan_tops_up_low_allowances_and_missing_operators() {
+        let (config, neg_risk_config) = configs();
+        let approvals = ApprovalConfig {
+            mode: ApprovalMode::DryRun,
+            allowance: U256::MAX,
+            top_up_below: U256::from(1_000_000_000u64),
+        };
+        // Allowance, then operator approval, for each spender in turn.
+        let provider = mock_calls(&[
+            U256::MAX,
+            U256::from(1),
+            U256::from(999_999_999u64),
+            U256::from(1),
+            U256::MAX,
+            U256::ZERO,
+        ]);
+        let plan = plan_approvals(
+            &provider,
+            Address::ZERO,
+            config,
+            neg_risk_config,
+            &approvals,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            plan.changes,
+            [
+                ApprovalChange::Allowance {
+                    spender: "NegRisk Exchange",
+                    address: neg_risk_config.exchange,
+                    token: neg_risk_config.collateral,
+                    current: U256::from(999_999_999u64),
+                    target: U256::MAX,
+                },
+                ApprovalChange::Operator {
+                    operator: "NegRiskAdapter",
+                    address: NEG_RISK_ADAPTER_ADDRESS,
+                    token: neg_risk_config.conditional_tokens,
+                    approved: true,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn revocations_cover_every_standing_approval() {
+        let (config, neg_risk_config) = configs();
+        // The ConditionalTokens contract is only checked for an allowance.
+        let provider = mock_calls(&[
+            U256::ZERO,
+            U256::from(1),
+            U256::ZERO,
+            U256::ZERO,
+            U256::ZERO,
+            U256::ZERO,
+            U256::from(5),
+        ]);
+        let plan = plan_revocations(&provider, Address::ZERO, config, neg_risk_config)
+            .await
+            .unwrap();
+        assert_eq!(
+            plan.changes,
+            [
+                ApprovalChange::Operator {
+                    operator: "Exchange",
+                    address: config.exchange,
+                    token: config.conditional_tokens,
+                    approved: false,
+                },
+                ApprovalChange::Allowance {
+                    spender: "ConditionalTokens",
+                    address: config.conditional_tokens,
+                    token: config.collateral,
+                    current: U256::from(5),
+                    target: U256::ZERO,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use alloy::primitives::{Address, Bytes, U256};
+use polymarket_client_sdk::{contract_config, POLYGON};
+use std::env;
+use std::io::{self, BufRead as _, Write as _};
+use std::str::FromStr as _;
+
+use crate::cli::CliArgs;
+use crate::ctf::simulate_then_send;
+use crate::tx::TxSender;
+use crate::util::{
+    apply_approvals, data_positions, format_balance, plan_revocations, wallet_provider, IERC1155,
+    IERC20, USDC_DECIMALS,
+};
+
+/// Asks on stdin before an irreversible action, unless `--yes true` was passed.
+fn confirm(args: &CliArgs, prompt: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    if args.parse_or("yes", false)? {
+        return Ok(true);
+    }
+    print!("{} Type 'yes' to continue: ", prompt);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(answer.trim().eq_ignore_ascii_case("yes"))
+}
+
+/// `revoke [--dry-run true] [--yes true]`: zeroes every USDC allowance and unsets every
+/// ConditionalTokens operator approval the wallet has granted to Polymarket contracts.
+pub async fn run_revoke(args: &CliArgs) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let dry_run = args.parse_or("dry-run", false)?;
+    let (provider, funder) = wallet_provider().await?;
+    let owner = funder.address();
+    let sender = TxSender::from_env(funder)?;
+    let config = contract_config(POLYGON, false).ok_or("No contract config for Polygon")?;
+    let neg_risk_config = contract_config(POLYGON, true).ok_or("No contract config for Polygon")?;
+
+    let plan = plan_revocations(&provider, owner, config, neg_risk_config).await?;
+    if plan.is_empty() {
+        println!("Nothing to revoke for {}", owner);
+        return Ok(());
+    }
+    println!("Revocations for {}:", owner);
+    plan.print();
+    if dry_run {
+        println!("Dry run: nothing was sent");
+        return Ok(());
+    }
+    if !confirm(args, &format!("Send {} revocations?", plan.changes.len()))? {
+        println!("Aborted");
+        return Ok(());
+    }
+    apply_approvals(&provider, &sender, owner, &plan).await?;
+    println!("Revoked {} approvals", plan.changes.len());
+    Ok(())
+}
+
+/// `sweep-wallet [--to ADDRESS] [--shares true] [--dry-run true] [--yes true]`: transfers the
+/// wallet's whole USDC balance, and with `--shares` every conditional token balance the data API
+/// knows of, to `--to` or `COLD_WALLET_ADDRESS`. Both transfers are simulated before sending;
+/// with `--dry-run` only the simulation runs.
+pub async fn run_sweep(args: &CliArgs) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let dry_run = args.parse_or("dry-run", false)?;
+    let include_shares = args.parse_or("shares", false)?;
+    let to = match args.get("to") {
+        Some(to) => to.to_string(),
+        None => {
+            env::var("COLD_WALLET_ADDRESS").map_err(|_| "Pass --to or set COLD_WALLET_ADDRESS")?
+        }
+    };
+    let to = Address::from_str(to.trim())?;
+    let (provider, funder) = wallet_provider().await?;
+    let owner = funder.address();
+    let sender = TxSender::from_env(funder)?;
+    if to == owner {
+        return Err("The cold address is the bot's own wallet".into());
+    }
+    let config = contract_config(POLYGON, false).ok_or("No contract config for Polygon")?;
+
+    let usdc = IERC20::new(config.collateral, &provider);
+    let balance = usdc.balanceOf(owner).call().await?;
+
+    let ctf = IERC1155::new(config.conditional_tokens, &provider);
+    let mut ids = Vec::new();
+    let mut amounts = Vec::new();
+    if include_shares {
+        for position in data_positions(owner, false).await? {
+            let id = U256::from_str_radix(&position.asset, 10)?;
+            let amount = ctf.balanceOf(owner, id).call().await?;
+            if amount.is_zero() || ids.contains(&id) {
+                continue;
+            }
+            println!(
+                "- {} shares of {} (outcome {})",
+                format_balance(amount, USDC_DECIMALS),
+                position.title,
+                position.outcome_index
+            );
+            ids.push(id);
+            amounts.push(amount);
+        }
+    }
+
+    println!(
+        "Sweeping {} USDC{} from {} to {}",
+        format_balance(balance, USDC_DECIMALS),
+        if include_shares {
+            format!(" and {} share balances", ids.len())
+        } else {
+            String::new()
+        },
+        owner,
+        to
+    );
+    if balance.is_zero() && ids.is_empty() {
+        println!("Nothing to sweep");
+        return Ok(());
+    }
+    if !dry_run && !confirm(args, "Transfer these funds?")? {
+        println!("Aborted");
+        return Ok(());
+    }
+
+    if !ids.is_empty() {
+        let call = ctf.safeBatchTransferFrom(owner, to, ids, amounts, Bytes::new());
+        match simulate_then_send(&sender, call, owner, dry_run).await? {
+            Some(tx_hash) => println!("Shares transferred in tx {}", tx_hash),
+            None => println!("Share transfer simulated successfully"),
+        }
+    }
+    if !balance.is_zero() {
+        match simulate_then_send(&sender, usdc.transfer(to, balance), owner, dry_run).await? {
+            Some(tx_hash) => println!("USDC transferred in tx {}", tx_hash),
+            None => println!("USDC transfer simulated successfully"),
+        }
+    }
+    if dry_run {
+        println!("Dry run: nothing was sent");
+    }
+    Ok(())
+}